@@ -1,245 +1,950 @@
-use std::{borrow::Cow, collections::HashMap, str::FromStr, sync::Arc};
-
-use anyhow::{Result, anyhow};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, LazyLock, RwLock, RwLockReadGuard},
+    time::Duration,
+};
 
 use base64::{Engine, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use regex::Regex;
 use reqwest::{
-    Client, Url,
-    cookie::{self, Jar},
-    header::HeaderMap,
+    RequestBuilder, Response, StatusCode, Url,
+    cookie::CookieStore as _,
+    header::{COOKIE, HeaderMap},
 };
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    api::{
+        endpoint::RadikoEndpoint,
+        http::RadikoHttpClient,
+        session_store::{PersistedSession, SessionStore},
+    },
+    dto::login_json::LoginResponse,
+    error::{RadikoError, Result},
+    models::{account::AccountInfo, area::Area},
+    utils::{ensure_success, md5_hex},
+};
+
+/// JP1〜JP9の1桁のarea_idも含める
+static AREA_ID_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bJP[0-9]{1,2}\b").expect("valid area_id pattern"));
+
+// https://github.com/miyagawa/ripdiko/blob/e9080f99c4c45b112256d822802f3dd56ab908f1/bin/ripdiko#L66
+static AUTH_KEY_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"new RadikoJSPlayer\(.*?,.*?,.'(?P<auth_key>\w+)'").expect("valid auth_key pattern")
+});
+
+/// radikoの認証トークンの有効期限は公開されていないため、経験的に問題の出ない時間で期限切れとみなす
+const TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// radiko_sessionの有効期限も公開されていないので、セッションファイルに残すために長めの期限を付与する
+/// 実際に有効かどうかは再利用時にログインチェックで確認する
+const SESSION_COOKIE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
 
-use crate::api::endpoint::RadikoEndpoint;
+const MOBILE_APP: &str = "aSmartPhone7a";
+const MOBILE_APP_VERSION: &str = "8.1.3";
+/// "{Android APIレベル}.{端末のモデル名}"
+const MOBILE_DEVICE: &str = "33.Pixel 7";
+/// 都道府県庁の所在地そのままではなく、実際の端末のように送信する座標を少しずらす
+const LOCATION_JITTER: f64 = 0.025;
 
 #[derive(Debug, Clone)]
 pub struct RadikoAuthManager {
     inner: Arc<RadikoAuthManagerRef>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct RadikoAuthManagerRef {
+    http: RadikoHttpClient,
+    endpoint: RadikoEndpoint,
+    /// ログアウトすると破棄され、以降は非エリアフリーとして再認証する
+    credentials: RwLock<Option<Credentials>>,
+    strategy: AuthStrategy,
+    session_store: Option<SessionStore>,
+    session: RwLock<AuthSession>,
+    /// 同時に401/403を受けたリクエストが一斉に再認証しないようにする
+    reauth_lock: Mutex<()>,
+}
+
+/// 認証の初期化オプション
+#[derive(Debug, Clone, Default)]
+pub struct AuthOptions {
+    pub mail: Option<SecretString>,
+    pub pass: Option<SecretString>,
+    pub strategy: AuthStrategy,
+    pub session_store: Option<SessionStore>,
+}
+
+/// auth1/auth2での認証方式
+#[derive(Debug, Clone, Default)]
+pub enum AuthStrategy {
+    /// ブラウザ(pc_html5)として認証する。エリアは接続元のIPアドレスから判定される
+    #[default]
+    Pc,
+    /// Androidアプリとして認証する。位置情報を送信して指定したエリアのトークンを取得する
+    Mobile(MobileAuth),
+}
+
+/// Androidアプリとして認証するための設定
+/// アプリに埋め込まれているフルキーはcrateに含めていないため、利用者が用意したものを渡す
+#[derive(Clone)]
+pub struct MobileAuth {
+    area: Area,
+    full_key: Arc<Vec<u8>>,
+    app_version: String,
+    device: String,
+}
+
+impl fmt::Debug for MobileAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MobileAuth")
+            .field("area", &self.area)
+            .field("full_key", &"[REDACTED]")
+            .field("app_version", &self.app_version)
+            .field("device", &self.device)
+            .finish()
+    }
+}
+
+impl MobileAuth {
+    /// areaの都道府県庁の所在地から認証する
+    pub fn new(area: Area, full_key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            area,
+            full_key: Arc::new(full_key.into()),
+            app_version: MOBILE_APP_VERSION.to_string(),
+            device: MOBILE_DEVICE.to_string(),
+        }
+    }
+
+    /// Base64エンコードされたフルキーから設定する
+    pub fn from_base64_key(area: Area, full_key: &str) -> Result<Self> {
+        let full_key = general_purpose::STANDARD
+            .decode(full_key.trim())
+            .map_err(|e| RadikoError::InvalidArgument(format!("invalid full key: {}", e)))?;
+        Ok(Self::new(area, full_key))
+    }
+
+    pub fn with_app_version(mut self, app_version: &str) -> Self {
+        self.app_version = app_version.to_string();
+        self
+    }
+
+    pub fn with_device(mut self, device: &str) -> Self {
+        self.device = device.to_string();
+        self
+    }
+
+    pub fn area(&self) -> Area {
+        self.area
+    }
+
+    pub fn area_id(&self) -> &'static str {
+        self.area.id()
+    }
+
+    /// X-Radiko-Locationの値 "{緯度},{経度},gps"
+    fn location(&self) -> String {
+        let (latitude, longitude) = self.area.location();
+        let mut rng = rand::rng();
+        format!(
+            "{:.6},{:.6},gps",
+            latitude + rng.random_range(-LOCATION_JITTER..LOCATION_JITTER),
+            longitude + rng.random_range(-LOCATION_JITTER..LOCATION_JITTER)
+        )
+    }
+}
+
+/// auth1/auth2で名乗るアプリの情報
+/// 認証トークンはこの情報と紐付くため、認証済みリクエストでも同じものを名乗る
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AppIdentity {
+    app: String,
+    app_version: String,
+    user: String,
+    device: String,
+    /// モバイルアプリの場合のみ送信する
+    location: Option<String>,
+}
+
+impl AppIdentity {
+    fn pc() -> Self {
+        Self {
+            app: "pc_html5".to_string(),
+            app_version: "0.0.1".to_string(),
+            user: "dummy_user".to_string(),
+            device: "pc".to_string(),
+            location: None,
+        }
+    }
+
+    fn mobile(mobile: &MobileAuth) -> Self {
+        Self {
+            app: MOBILE_APP.to_string(),
+            app_version: mobile.app_version.clone(),
+            user: crate::utils::generate_md5_hash(),
+            device: mobile.device.clone(),
+            location: Some(mobile.location()),
+        }
+    }
+
+    fn headers(&self) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Radiko-App", self.app.parse()?);
+        headers.insert("X-Radiko-App-Version", self.app_version.parse()?);
+        headers.insert("X-Radiko-User", self.user.parse()?);
+        headers.insert("X-Radiko-Device", self.device.parse()?);
+        Ok(headers)
+    }
+
+    /// 認証済みリクエストに付与するヘッダー
+    fn auth_headers(&self, auth_token: &str, partial_key: &str) -> Result<HeaderMap> {
+        let mut headers = self.headers()?;
+        headers.insert("X-Radiko-Authtoken", auth_token.parse()?);
+        headers.insert("X-Radiko-Partialkey", partial_key.parse()?);
+        Ok(headers)
+    }
+}
+
+/// auth1/auth2の結果
+struct Authorization {
+    area_id: String,
+    identity: AppIdentity,
+    auth_key: Option<String>,
+    auth_token: String,
+    partial_key: String,
+}
+
+/// エリアフリーのログイン情報
+#[derive(Debug, Clone)]
+struct Credentials {
+    mail: SecretString,
+    pass: SecretString,
+}
+
+/// auth1/auth2で得られる再認証の度に入れ替わる情報
+#[derive(Debug, Clone)]
+struct AuthSession {
     area_id: String,
     area_free: bool,
-    http_client: Client,
     auth_token: String,
+    partial_key: String,
+    /// auth1/auth2で名乗ったアプリの情報
+    identity: AppIdentity,
+    /// 認証済みリクエストに付与するヘッダー
+    auth_headers: HeaderMap,
     stream_lsid: String,
-    mail: Option<SecretString>,
-    pass: Option<SecretString>,
+    issued_at: DateTime<Utc>,
+    /// playerCommon.jsから抽出した認証キー。モバイルアプリとして認証した場合はNone
+    auth_key: Option<String>,
+    cookie_store: Arc<CookieStoreMutex>,
+    /// エリアフリーでログインしている場合のアカウント情報
+    account_info: Option<AccountInfo>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct LoginResponse {
-    twitter_name: Option<String>,
-    status: String,
-    unpaid: String,
-    radiko_session: String,
-    areafree: String,
-    member_ukey: String,
-    facebook_name: Option<String>,
-    privileges: Vec<String>,
-    paid_member: String,
+/// 再認証やセッションファイルから引き継いで、ログインやplayerCommon.jsの取得を省略するための情報
+#[derive(Default)]
+struct AuthCache {
+    auth_key: Option<String>,
+    cookie_store: Option<Arc<CookieStoreMutex>>,
+    account_info: Option<AccountInfo>,
 }
 
 impl RadikoAuthManager {
-    pub async fn new() -> Self {
-        Self::init(None, None).await.unwrap()
+    #[allow(dead_code)]
+    pub async fn new(http: RadikoHttpClient, endpoint: RadikoEndpoint) -> Result<Self> {
+        Self::with_options(http, endpoint, AuthOptions::default()).await
     }
 
-    pub async fn new_area_free(mail: &str, pass: &str) -> Self {
-        Self::init(
-            Some(SecretString::new(mail.into())),
-            Some(SecretString::new(pass.into())),
+    #[allow(dead_code)]
+    pub async fn new_area_free(
+        http: RadikoHttpClient,
+        endpoint: RadikoEndpoint,
+        mail: &str,
+        pass: &str,
+    ) -> Result<Self> {
+        Self::with_options(
+            http,
+            endpoint,
+            AuthOptions {
+                mail: Some(SecretString::new(mail.into())),
+                pass: Some(SecretString::new(pass.into())),
+                ..Default::default()
+            },
         )
         .await
-        .unwrap()
     }
 
-    pub fn area_id(&self) -> Cow<str> {
-        Cow::Borrowed(&self.inner.area_id)
+    /// セッションファイルが有効であれば再利用し、そうでなければ認証する
+    pub async fn with_options(
+        http: RadikoHttpClient,
+        endpoint: RadikoEndpoint,
+        options: AuthOptions,
+    ) -> Result<Self> {
+        let AuthOptions {
+            mail,
+            pass,
+            strategy,
+            session_store,
+        } = options;
+        let credentials = match (mail, pass) {
+            (Some(mail), Some(pass)) => Some(Credentials { mail, pass }),
+            _ => None,
+        };
+        let account = Self::account_id(credentials.as_ref());
+        let persisted = session_store
+            .as_ref()
+            .and_then(SessionStore::load)
+            .filter(|persisted| persisted.account == account)
+            .filter(|persisted| Self::is_same_strategy(persisted, &strategy));
+
+        let restored = persisted.as_ref().and_then(Self::restore_session);
+        let session = match restored {
+            Some(session) => session,
+            None => {
+                let cache = persisted
+                    .map(|persisted| AuthCache {
+                        auth_key: persisted.auth_key,
+                        cookie_store: Some(Arc::new(CookieStoreMutex::new(persisted.cookies))),
+                        account_info: persisted.account_info,
+                    })
+                    .unwrap_or_default();
+                Self::authenticate(&http, &endpoint, &strategy, credentials.as_ref(), cache).await?
+            }
+        };
+
+        Self::from_session(
+            http,
+            endpoint,
+            credentials,
+            strategy,
+            session_store,
+            session,
+        )
+    }
+
+    /// 認証済みのセッションからマネージャーを作り、セッションファイルに保存する
+    fn from_session(
+        http: RadikoHttpClient,
+        endpoint: RadikoEndpoint,
+        credentials: Option<Credentials>,
+        strategy: AuthStrategy,
+        session_store: Option<SessionStore>,
+        session: AuthSession,
+    ) -> Result<Self> {
+        let auth_manager = Self {
+            inner: Arc::new(RadikoAuthManagerRef {
+                http,
+                endpoint,
+                credentials: RwLock::new(credentials),
+                strategy,
+                session_store,
+                session: RwLock::new(session),
+                reauth_lock: Mutex::new(()),
+            }),
+        };
+        auth_manager.persist_session()?;
+
+        Ok(auth_manager)
+    }
+
+    fn credentials(&self) -> Option<Credentials> {
+        self.inner
+            .credentials
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn session(&self) -> RwLockReadGuard<'_, AuthSession> {
+        self.inner
+            .session
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn area_id(&self) -> String {
+        self.session().area_id.clone()
     }
 
     pub fn area_free(&self) -> bool {
-        self.inner.area_free
+        self.session().area_free
+    }
+
+    pub fn auth_token(&self) -> String {
+        self.session().auth_token.clone()
+    }
+
+    /// エリアフリーでログインしている場合のアカウント情報
+    pub fn account_info(&self) -> Option<AccountInfo> {
+        self.session().account_info.clone()
+    }
+
+    pub fn lsid(&self) -> String {
+        self.session().stream_lsid.clone()
+    }
+
+    /// 認証トークンの発行日時
+    pub fn issued_at(&self) -> DateTime<Utc> {
+        self.session().issued_at
+    }
+
+    /// 認証トークンの発行からの経過時間
+    pub fn token_age(&self) -> Duration {
+        Utc::now()
+            .signed_duration_since(self.issued_at())
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    /// 認証トークンが失効している可能性が高い場合にtrue
+    pub fn is_probably_expired(&self) -> bool {
+        self.token_age() >= TOKEN_LIFETIME
+    }
+
+    /// 認証ヘッダーを付与したGETリクエストを送信する
+    /// 401/403が返却された場合は1度だけ再認証してからリクエストし直す
+    pub async fn send_authed(&self, url: &str) -> Result<Response> {
+        let auth_token = self.auth_token();
+        let response = self.send_with_auth_headers(url).await?;
+        if !matches!(
+            response.status(),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return ensure_success(response).await;
+        }
+
+        self.reauthenticate_if_unchanged(&auth_token).await?;
+        ensure_success(self.send_with_auth_headers(url).await?).await
+    }
+
+    async fn send_with_auth_headers(&self, url: &str) -> Result<Response> {
+        let auth_headers = self.session().auth_headers.clone();
+        self.inner
+            .http
+            .send(self.inner.http.get(url).headers(auth_headers))
+            .await
     }
 
-    pub fn http_client(&self) -> Client {
-        self.inner.http_client.clone()
+    /// 認証トークンを更新する
+    /// 保持しているradiko_sessionと認証キーは再利用し、無効になっていた場合のみログインからやり直す
+    pub async fn reauthenticate(&self) -> Result<()> {
+        let _guard = self.inner.reauth_lock.lock().await;
+        self.replace_session().await
     }
 
-    pub fn auth_token(&self) -> Cow<str> {
-        Cow::Borrowed(&self.inner.auth_token)
+    /// 他のリクエストが先に再認証を済ませていれば何もしない
+    async fn reauthenticate_if_unchanged(&self, stale_auth_token: &str) -> Result<()> {
+        let _guard = self.inner.reauth_lock.lock().await;
+        if self.auth_token() != stale_auth_token {
+            return Ok(());
+        }
+        self.replace_session().await
+    }
+
+    async fn replace_session(&self) -> Result<()> {
+        let cache = {
+            let session = self.session();
+            AuthCache {
+                auth_key: session.auth_key.clone(),
+                cookie_store: Some(session.cookie_store.clone()),
+                account_info: session.account_info.clone(),
+            }
+        };
+        let session = Self::authenticate(
+            &self.inner.http,
+            &self.inner.endpoint,
+            &self.inner.strategy,
+            self.credentials().as_ref(),
+            cache,
+        )
+        .await?;
+        *self
+            .inner
+            .session
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = session;
+        self.persist_session()
     }
 
-    pub fn lsid(&self) -> Cow<str> {
-        Cow::Borrowed(&self.inner.stream_lsid)
+    /// エリアフリーのセッションをログアウトして非エリアフリーとして再認証する
+    /// ログアウトのリクエストが失敗した場合も手元のcookieとログイン情報は破棄する
+    pub async fn logout(&self) -> Result<()> {
+        let _guard = self.inner.reauth_lock.lock().await;
+        if self.credentials().is_none() {
+            return Ok(());
+        }
+
+        let cookie_store = self.session().cookie_store.clone();
+        let logout_url = self.inner.endpoint.logout_endpoint();
+        let logout_result = async {
+            let request = Self::with_cookie(
+                self.inner.http.post(&logout_url),
+                &cookie_store,
+                &logout_url,
+            )?;
+            ensure_success(self.inner.http.send(request).await?).await?;
+            Ok(())
+        }
+        .await;
+
+        cookie_store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+        *self
+            .inner
+            .credentials
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        if let Some(session_store) = &self.inner.session_store {
+            session_store.clear()?;
+        }
+
+        self.replace_session().await?;
+        logout_result
     }
 
+    /// ログインからやり直した新しいマネージャーを返す
+    /// セッションファイルを設定している場合は新しいセッションで上書きする
     #[allow(dead_code)]
     pub async fn refresh_auth(&self) -> Result<Self> {
-        Self::init(self.inner.mail.clone(), self.inner.pass.clone()).await
+        let credentials = self.credentials();
+        let session = Self::authenticate(
+            &self.inner.http,
+            &self.inner.endpoint,
+            &self.inner.strategy,
+            credentials.as_ref(),
+            AuthCache::default(),
+        )
+        .await?;
+        Self::from_session(
+            self.inner.http.clone(),
+            self.inner.endpoint.clone(),
+            credentials,
+            self.inner.strategy.clone(),
+            self.inner.session_store.clone(),
+            session,
+        )
     }
 
-    async fn init(mail: Option<SecretString>, pass: Option<SecretString>) -> Result<Self> {
-        let is_area_free = mail.is_some() && pass.is_some();
-        let auth1_url = RadikoEndpoint::auth1_endpoint();
-        let auth2_url = RadikoEndpoint::auth2_endpoint();
-        let auth_key = Self::get_public_auth_key().await;
+    fn account_id(credentials: Option<&Credentials>) -> Option<String> {
+        credentials.map(|credentials| md5_hex(credentials.mail.expose_secret()))
+    }
 
-        // get area_id
-        let response_body = Client::new()
-            .get(RadikoEndpoint::area_id_endpoint())
-            .send()
-            .await?
-            .text()
-            .await?;
+    /// 保存されているセッションが同じ認証方式で得たものであればtrue
+    fn is_same_strategy(persisted: &PersistedSession, strategy: &AuthStrategy) -> bool {
+        match strategy {
+            AuthStrategy::Pc => persisted.auth_key.is_some(),
+            AuthStrategy::Mobile(mobile) => {
+                persisted.auth_key.is_none()
+                    && persisted.area_id.as_deref() == Some(mobile.area_id())
+            }
+        }
+    }
+
+    /// 期限内の認証トークンが保存されていれば通信せずにセッションを復元する
+    fn restore_session(persisted: &PersistedSession) -> Option<AuthSession> {
+        let issued_at = persisted.issued_at?;
+        let token_age = Utc::now().signed_duration_since(issued_at).to_std().ok()?;
+        if token_age >= TOKEN_LIFETIME {
+            return None;
+        }
+        let auth_token = persisted.auth_token.clone()?;
+        let partial_key = persisted.partial_key.clone()?;
+        // 名乗ったアプリの情報を保存していない古いセッションはブラウザとして認証したものに限り復元する
+        let identity = match &persisted.identity {
+            Some(identity) => identity.clone(),
+            None if persisted.auth_key.is_some() => AppIdentity::pc(),
+            None => return None,
+        };
+
+        Some(AuthSession {
+            area_id: persisted.area_id.clone()?,
+            area_free: persisted.account.is_some(),
+            auth_headers: identity.auth_headers(&auth_token, &partial_key).ok()?,
+            identity,
+            auth_token,
+            partial_key,
+            stream_lsid: crate::utils::generate_md5_hash(),
+            issued_at,
+            auth_key: persisted.auth_key.clone(),
+            cookie_store: Arc::new(CookieStoreMutex::new(persisted.cookies.clone())),
+            account_info: persisted.account_info.clone(),
+        })
+    }
 
-        let area_id_pattern = Regex::new(r"[A-Z]{2}[0-9]{2}")?;
-        let Some(area_id_caps) = area_id_pattern.captures(&response_body) else {
-            panic!("failed get area_id. not found pattern area_id");
+    fn persist_session(&self) -> Result<()> {
+        let Some(session_store) = &self.inner.session_store else {
+            return Ok(());
         };
-        let default_area_id = area_id_caps[0].to_string();
+        let session = self.session();
+        let cookies = session
+            .cookie_store
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        session_store.save(&PersistedSession {
+            account: Self::account_id(self.credentials().as_ref()),
+            cookies,
+            auth_key: session.auth_key.clone(),
+            auth_token: Some(session.auth_token.clone()),
+            partial_key: Some(session.partial_key.clone()),
+            area_id: Some(session.area_id.clone()),
+            identity: Some(session.identity.clone()),
+            issued_at: Some(session.issued_at),
+            account_info: session.account_info.clone(),
+        })
+    }
+
+    async fn authenticate(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        strategy: &AuthStrategy,
+        credentials: Option<&Credentials>,
+        cache: AuthCache,
+    ) -> Result<AuthSession> {
+        let is_area_free = credentials.is_some();
 
         // login
-        let cookie: Arc<cookie::Jar> = if is_area_free {
-            RadikoAuthManager::login(
-                &mail.clone().unwrap().expose_secret(),
-                &pass.clone().unwrap().expose_secret(),
-            )
-            .await?
-        } else {
-            Arc::new(Jar::default())
+        // アカウント情報はログイン時のレスポンスからしか得られないので、無い場合はログインし直す
+        let (cookie_store, account_info) = match credentials {
+            Some(credentials) => match (cache.cookie_store, cache.account_info) {
+                (Some(cookie_store), Some(account_info))
+                    if Self::check_login(http, endpoint, &cookie_store).await? =>
+                {
+                    (cookie_store, Some(account_info))
+                }
+                _ => {
+                    let (cookie_store, account_info) = RadikoAuthManager::login(
+                        http,
+                        endpoint,
+                        credentials.mail.expose_secret(),
+                        credentials.pass.expose_secret(),
+                    )
+                    .await?;
+                    (cookie_store, Some(account_info))
+                }
+            },
+            None => (Arc::new(CookieStoreMutex::default()), None),
         };
-        let logined_client = Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-            .cookie_provider(cookie.clone())
-            .build()?;
+
+        let authorization = match strategy {
+            AuthStrategy::Pc => {
+                Self::authorize_pc(http, endpoint, &cookie_store, cache.auth_key).await?
+            }
+            AuthStrategy::Mobile(mobile) => {
+                Self::authorize_mobile(http, endpoint, &cookie_store, mobile).await?
+            }
+        };
+
+        // cookieに設定されるa_expはmd5ハッシュ現在日時から適当に生成しているだけ
+        // 適当なMD5ハッシュをlsidにしてブラウザと同じエンドポイントでストリーム開けるか試す
+        // https://radiko.jp/apps/js/common.js?_=20250306
+        let lsid = crate::utils::generate_md5_hash();
+
+        Ok(AuthSession {
+            area_id: authorization.area_id,
+            area_free: is_area_free,
+            auth_headers: authorization
+                .identity
+                .auth_headers(&authorization.auth_token, &authorization.partial_key)?,
+            identity: authorization.identity,
+            auth_token: authorization.auth_token,
+            partial_key: authorization.partial_key,
+            stream_lsid: lsid,
+            issued_at: Utc::now(),
+            auth_key: authorization.auth_key,
+            cookie_store,
+            account_info,
+        })
+    }
+
+    /// ブラウザとして認証する。エリアはradiko.jp/area/で判定されたものになる
+    async fn authorize_pc(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        cookie_store: &CookieStoreMutex,
+        cached_auth_key: Option<String>,
+    ) -> Result<Authorization> {
+        let is_cached = cached_auth_key.is_some();
+        let mut auth_key = match cached_auth_key {
+            Some(auth_key) => auth_key,
+            None => Self::get_public_auth_key(http, endpoint).await?,
+        };
+
+        // get area_id
+        let response_body = http.text(http.get(&endpoint.area_id_endpoint())).await?;
+        let default_area_id = Self::parse_area_id(&response_body)?;
+
+        // キャッシュした認証キーが更新されていた場合はplayerCommon.jsから取り直す
+        let identity = AppIdentity::pc();
+        let (auth_token, partial_key, _) =
+            match Self::authorize(http, endpoint, cookie_store, &identity, auth_key.as_bytes())
+                .await
+            {
+                Err(RadikoError::AuthFailed(_)) if is_cached => {
+                    auth_key = Self::get_public_auth_key(http, endpoint).await?;
+                    Self::authorize(http, endpoint, cookie_store, &identity, auth_key.as_bytes())
+                        .await?
+                }
+                result => result?,
+            };
+
+        Ok(Authorization {
+            area_id: default_area_id,
+            identity,
+            auth_key: Some(auth_key),
+            auth_token,
+            partial_key,
+        })
+    }
+
+    /// Androidアプリとして指定したエリアの位置情報で認証する
+    async fn authorize_mobile(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        cookie_store: &CookieStoreMutex,
+        mobile: &MobileAuth,
+    ) -> Result<Authorization> {
+        let identity = AppIdentity::mobile(mobile);
+        let (auth_token, partial_key, area_id) =
+            Self::authorize(http, endpoint, cookie_store, &identity, &mobile.full_key).await?;
+        if area_id != mobile.area_id() {
+            return Err(RadikoError::AuthFailed(format!(
+                "requested area {} but authorized as {}",
+                mobile.area_id(),
+                area_id
+            )));
+        }
+
+        Ok(Authorization {
+            area_id,
+            identity,
+            auth_key: None,
+            auth_token,
+            partial_key,
+        })
+    }
+
+    /// auth1/auth2を行い、認証トークン、パーシャルキー、auth2で判定されたarea_idを返す
+    async fn authorize(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        cookie_store: &CookieStoreMutex,
+        identity: &AppIdentity,
+        auth_key: &[u8],
+    ) -> Result<(String, String, String)> {
+        let auth1_url = endpoint.auth1_endpoint();
+        let auth2_url = endpoint.auth2_endpoint();
 
         // auth1
-        let mut headers = HeaderMap::new();
-        headers.insert("X-Radiko-App", "pc_html5".parse()?);
-        headers.insert("X-Radiko-App-Version", "0.0.1".parse()?);
-        headers.insert("X-Radiko-User", "dummy_user".parse()?);
-        headers.insert("X-Radiko-Device", "pc".parse()?);
-
-        let res_auth1 = logined_client
-            .get(auth1_url)
-            .headers(headers)
-            .send()
+        let res_auth1 = http
+            .send(
+                Self::with_cookie(http.get(&auth1_url), cookie_store, &auth1_url)?
+                    .headers(identity.headers()?),
+            )
             .await?;
+        if !res_auth1.status().is_success() {
+            return Err(RadikoError::AuthFailed(format!(
+                "auth1 request: {}",
+                res_auth1.text().await?
+            )));
+        }
 
         // auth2
-        let auth_token = res_auth1
-            .headers()
-            .get("X-Radiko-Authtoken")
-            .unwrap()
-            .to_str()?;
-        let offset = res_auth1
-            .headers()
-            .get("X-Radiko-KeyOffset")
-            .unwrap()
-            .to_str()?
-            .parse::<usize>()?;
-        let length = res_auth1
-            .headers()
-            .get("X-Radiko-KeyLength")
-            .unwrap()
-            .to_str()?
-            .parse::<usize>()?;
-        let partial_key = general_purpose::STANDARD.encode(&auth_key[offset..offset + length]);
+        let auth_token = Self::response_header(&res_auth1, "X-Radiko-Authtoken")?;
+        let offset = Self::key_header(&res_auth1, "X-Radiko-KeyOffset")?;
+        let length = Self::key_header(&res_auth1, "X-Radiko-KeyLength")?;
+        let partial_key = general_purpose::STANDARD.encode(
+            offset
+                .checked_add(length)
+                .and_then(|end| auth_key.get(offset..end))
+                .ok_or_else(|| {
+                    RadikoError::AuthFailed(format!(
+                        "key offset out of range. offset: {}, length: {}",
+                        offset, length
+                    ))
+                })?,
+        );
 
-        let mut headers = HeaderMap::new();
+        let mut headers = identity.headers()?;
         headers.insert("X-Radiko-Authtoken", auth_token.parse()?);
         headers.insert("X-Radiko-Partialkey", partial_key.parse()?);
-        headers.insert("X-Radiko-User", "dummy_user".parse()?);
-        headers.insert("X-Radiko-Device", "pc".parse()?);
-
-        let res_auth2 = logined_client
-            .get(&auth2_url)
-            .headers(headers.clone())
-            .send()
+        if let Some(location) = &identity.location {
+            headers.insert("X-Radiko-Location", location.parse()?);
+            headers.insert("X-Radiko-Connection", "wifi".parse()?);
+        }
+        let res_auth2 = http
+            .send(
+                Self::with_cookie(http.get(&auth2_url), cookie_store, &auth2_url)?.headers(headers),
+            )
             .await?;
-        if !res_auth2.status().is_success() {
-            return Err(anyhow!("error auth2 request: {}", res_auth2.text().await?));
+        let auth2_status = res_auth2.status();
+        let auth2_body = res_auth2.text().await?;
+        if !auth2_status.is_success() {
+            return Err(RadikoError::AuthFailed(format!(
+                "auth2 request: {}",
+                auth2_body
+            )));
         }
+        if auth2_body.trim_start().starts_with("OUT") {
+            return Err(RadikoError::OutOfArea(auth2_body));
+        }
+        // ex: "JP13,東京都,tokyo Japan"
+        let area_id = auth2_body
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
 
-        let authed_client = Client::builder()
-            .cookie_provider(cookie)
-            .default_headers(headers)
-            .build()?;
-
-        // cookieに設定されるa_expはmd5ハッシュ現在日時から適当に生成しているだけ
-        // 適当なMD5ハッシュをlsidにしてブラウザと同じエンドポイントでストリーム開けるか試す
-        // https://radiko.jp/apps/js/common.js?_=20250306
-        let lsid = crate::utils::generate_md5_hash();
+        Ok((auth_token, partial_key, area_id))
+    }
 
-        Ok(Self {
-            inner: Arc::new(RadikoAuthManagerRef {
-                area_id: default_area_id.to_string(),
-                area_free: is_area_free,
-                http_client: authed_client,
-                auth_token: auth_token.to_string(),
-                stream_lsid: lsid,
-                mail: mail,
-                pass: pass,
-            }),
+    /// ユーザー指定のClientにはcookie_providerを設定できないので、cookieはヘッダーとして明示的に付与する
+    fn with_cookie(
+        request: RequestBuilder,
+        cookie_store: &CookieStoreMutex,
+        url: &str,
+    ) -> Result<RequestBuilder> {
+        let url = Url::from_str(url).map_err(|e| RadikoError::InvalidArgument(e.to_string()))?;
+        Ok(match cookie_store.cookies(&url) {
+            Some(cookie) => request.header(COOKIE, cookie),
+            None => request,
         })
     }
 
-    async fn get_public_auth_key() -> String {
-        // https://github.com/miyagawa/ripdiko/blob/e9080f99c4c45b112256d822802f3dd56ab908f1/bin/ripdiko#L66
-        let url = "https://radiko.jp/apps/js/playerCommon.js";
-        let response_body = reqwest::get(url).await.unwrap().text().await.unwrap();
-        let auth_key_pattern =
-            regex::Regex::new(r"new RadikoJSPlayer\(.*?,.*?,.'(?P<auth_key>\w+)'").unwrap();
-        let Some(auth_key_caps) = auth_key_pattern.captures(&response_body) else {
-            panic!("failed get auth_key ")
-        };
+    /// radiko.jp/area/ のレスポンスからarea_idを取り出す
+    /// 国外からのアクセスの場合は `<span class="OUT">` が返却される
+    fn parse_area_id(response_body: &str) -> Result<String> {
+        if let Some(area) = AREA_ID_PATTERN
+            .find(response_body)
+            .and_then(|area_id| area_id.as_str().parse::<Area>().ok())
+        {
+            return Ok(area.id().to_string());
+        }
+        if response_body.contains("OUT") {
+            return Err(RadikoError::OutOfArea(response_body.to_string()));
+        }
+        Err(RadikoError::AreaDetectionFailed(format!(
+            "not found pattern area_id: {}",
+            response_body
+        )))
+    }
+
+    fn response_header(response: &Response, name: &'static str) -> Result<String> {
+        Ok(response
+            .headers()
+            .get(name)
+            .ok_or(RadikoError::MissingHeader(name))?
+            .to_str()?
+            .to_string())
+    }
+
+    fn key_header(response: &Response, name: &'static str) -> Result<usize> {
+        Self::response_header(response, name)?
+            .parse::<usize>()
+            .map_err(|e| RadikoError::InvalidHeader(format!("{}: {}", name, e)))
+    }
+
+    async fn get_public_auth_key(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+    ) -> Result<String> {
+        let response_body = http
+            .text(http.get(&endpoint.player_script_endpoint()))
+            .await?;
+
+        Self::parse_auth_key(&response_body)
+    }
+
+    fn parse_auth_key(player_script: &str) -> Result<String> {
+        let auth_key_caps = AUTH_KEY_PATTERN
+            .captures(player_script)
+            .ok_or(RadikoError::AuthKeyNotFound)?;
 
-        auth_key_caps["auth_key"].to_string()
+        Ok(auth_key_caps["auth_key"].to_string())
     }
 
-    async fn login(mail: &str, pass: &str) -> Result<Arc<cookie::Jar>> {
+    async fn login(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        mail: &str,
+        pass: &str,
+    ) -> Result<(Arc<CookieStoreMutex>, AccountInfo)> {
         let mut login_info = HashMap::new();
         login_info.insert("mail", mail);
         login_info.insert("pass", pass);
-        let login_res: LoginResponse = Client::new()
-            .post(RadikoEndpoint::login_endpoint())
-            .form(&login_info)
-            .send()
-            .await?
-            .json()
-            .await?;
-        let cookie = format!("radiko_session={}", login_res.radiko_session);
-        let jar = Arc::new(Jar::default());
-        jar.add_cookie_str(&cookie, &Url::from_str(RadikoEndpoint::RADIKO_HOST)?);
-
-        let login_check_res = Client::builder()
-            .cookie_provider(jar.clone())
-            .build()?
-            .get(RadikoEndpoint::LOGIN_CHECK_URL)
-            .send()
+        let login_res = http
+            .send(http.post(&endpoint.login_endpoint()).form(&login_info))
             .await?;
+        if !login_res.status().is_success() {
+            return Err(RadikoError::LoginFailed(format!(
+                "status: {}, body: {}",
+                login_res.status(),
+                login_res.text().await?
+            )));
+        }
+        let login_res: LoginResponse = serde_json::from_str(&login_res.text().await?)
+            .map_err(|e| RadikoError::LoginFailed(format!("unexpected login response: {}", e)))?;
+        let cookie = format!(
+            "radiko_session={}; Max-Age={}",
+            &login_res.radiko_session,
+            SESSION_COOKIE_MAX_AGE.as_secs()
+        );
+        let radiko_host = Url::from_str(&endpoint.radiko_host())
+            .map_err(|e| RadikoError::InvalidArgument(e.to_string()))?;
+        let mut cookie_store = CookieStore::default();
+        cookie_store
+            .parse(&cookie, &radiko_host)
+            .map_err(|e| RadikoError::LoginFailed(format!("invalid radiko_session: {}", e)))?;
+        let cookie_store = Arc::new(CookieStoreMutex::new(cookie_store));
 
-        if !login_check_res.status().is_success() {
-            return Err(anyhow!(
-                "login check failed: {}",
-                login_check_res.text().await?
-            ));
+        if !Self::check_login(http, endpoint, &cookie_store).await? {
+            return Err(RadikoError::LoginFailed("login check failed".to_string()));
         }
 
-        Ok(jar)
+        Ok((cookie_store, AccountInfo::from(login_res)))
+    }
+
+    /// radiko_sessionが有効であればtrue
+    async fn check_login(
+        http: &RadikoHttpClient,
+        endpoint: &RadikoEndpoint,
+        cookie_store: &CookieStoreMutex,
+    ) -> Result<bool> {
+        let login_check_url = endpoint.login_check_endpoint();
+        let login_check_res = http
+            .send(Self::with_cookie(
+                http.get(&login_check_url),
+                cookie_store,
+                &login_check_url,
+            )?)
+            .await?;
+
+        Ok(login_check_res.status().is_success())
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use crate::utils;
+    use crate::{
+        api::endpoint::EndpointConfig,
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_login_routes},
+        utils,
+    };
 
     use super::*;
+    use anyhow::Result;
     use std::env;
 
     #[tokio::test]
@@ -247,14 +952,21 @@ mod tests {
         utils::load_env();
         let mail = env::var("mail").expect("failed mail from dotenv");
         let pass = env::var("pass").expect("failed pass from dotenv");
-        let _ = RadikoAuthManager::login(&mail, &pass).await?;
+        let _ = RadikoAuthManager::login(
+            &RadikoHttpClient::default(),
+            &RadikoEndpoint::default(),
+            &mail,
+            &pass,
+        )
+        .await?;
 
         Ok(())
     }
 
     #[tokio::test]
     async fn init_radiko_auth_manager_test() -> Result<()> {
-        let radiko_auth_manager = RadikoAuthManager::new().await;
+        let radiko_auth_manager =
+            RadikoAuthManager::new(RadikoHttpClient::default(), RadikoEndpoint::default()).await?;
 
         println!("radiko_auth_manager: {:#?}", radiko_auth_manager);
 
@@ -263,7 +975,8 @@ mod tests {
 
     #[tokio::test]
     async fn refresh_auth_test() -> Result<()> {
-        let radiko_auth_manager = RadikoAuthManager::new().await;
+        let radiko_auth_manager =
+            RadikoAuthManager::new(RadikoHttpClient::default(), RadikoEndpoint::default()).await?;
         let refreshed_auth_manager = radiko_auth_manager.refresh_auth().await?;
 
         assert_ne!(
@@ -273,4 +986,217 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn init_with_mock_server_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+
+        let radiko_auth_manager =
+            RadikoAuthManager::new(RadikoHttpClient::default(), endpoint).await?;
+
+        assert_eq!(radiko_auth_manager.area_id(), "JP13");
+        assert_eq!(radiko_auth_manager.auth_token(), "mock_token");
+        let auth2_requests = server.requests_to("/v2/api/auth2");
+        assert_eq!(
+            auth2_requests[0].header("X-Radiko-Partialkey"),
+            Some(
+                general_purpose::STANDARD
+                    .encode("3c03b352e1ef2fd6")
+                    .as_str()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn key_offset_overflow_test() -> Result<()> {
+        let server = MockServer::start().await;
+        // 先に登録した応答から順に返される
+        server.route(
+            "/v2/api/auth1",
+            MockResponse::ok("")
+                .header("X-Radiko-Authtoken", "mock_token")
+                .header("X-Radiko-KeyOffset", &usize::MAX.to_string())
+                .header("X-Radiko-KeyLength", "16"),
+        );
+        mock_auth_routes(&server);
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+
+        let result = RadikoAuthManager::new(RadikoHttpClient::default(), endpoint).await;
+
+        assert!(matches!(result, Err(RadikoError::AuthFailed(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reuse_persisted_session_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        mock_login_routes(&server);
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let dir = tempfile::tempdir()?;
+        let options = AuthOptions {
+            mail: Some(SecretString::new("mail@example.com".into())),
+            pass: Some(SecretString::new("pass".into())),
+            session_store: Some(SessionStore::new(dir.path().join("session.json"))),
+            ..Default::default()
+        };
+
+        RadikoAuthManager::with_options(
+            RadikoHttpClient::default(),
+            endpoint.clone(),
+            options.clone(),
+        )
+        .await?;
+        assert_eq!(server.requests_to("/v4/api/member/login").len(), 1);
+
+        // 期限内のトークンが保存されていれば通信せずに復元する
+        let restored = RadikoAuthManager::with_options(
+            RadikoHttpClient::default(),
+            endpoint.clone(),
+            options.clone(),
+        )
+        .await?;
+        assert_eq!(restored.auth_token(), "mock_token");
+        assert!(restored.area_free());
+        assert!(
+            restored
+                .account_info()
+                .is_some_and(|account_info| account_info.can_listen_area_free())
+        );
+        assert_eq!(server.requests_to("/v2/api/auth1").len(), 1);
+
+        // 再認証ではradiko_sessionと認証キーを再利用する
+        restored.reauthenticate().await?;
+        assert_eq!(server.requests_to("/v4/api/member/login").len(), 1);
+        assert_eq!(server.requests_to("/apps/js/playerCommon.js").len(), 1);
+        assert_eq!(
+            server.requests_to("/v2/api/auth1")[1].header("cookie"),
+            Some("radiko_session=mock_session")
+        );
+
+        // 作り直したセッションも同じセッションファイルに保存する
+        let session_store = options.session_store.clone().unwrap();
+        session_store.clear()?;
+        restored.refresh_auth().await?;
+        assert_eq!(server.requests_to("/v4/api/member/login").len(), 2);
+        assert!(
+            session_store
+                .load()
+                .is_some_and(|persisted| persisted.auth_token.as_deref() == Some("mock_token"))
+        );
+
+        // 別アカウントのセッションは利用しない
+        RadikoAuthManager::with_options(
+            RadikoHttpClient::default(),
+            endpoint,
+            AuthOptions {
+                mail: Some(SecretString::new("other@example.com".into())),
+                ..options
+            },
+        )
+        .await?;
+        assert_eq!(server.requests_to("/v4/api/member/login").len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mobile_auth_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let full_key: Vec<u8> = (0..=255).collect();
+        let options = AuthOptions {
+            strategy: AuthStrategy::Mobile(MobileAuth::new(Area::Tokyo, full_key.clone())),
+            ..Default::default()
+        };
+
+        let radiko_auth_manager =
+            RadikoAuthManager::with_options(RadikoHttpClient::default(), endpoint.clone(), options)
+                .await?;
+
+        assert_eq!(radiko_auth_manager.area_id(), "JP13");
+        assert!(server.requests_to("/area/").is_empty());
+        assert!(server.requests_to("/apps/js/playerCommon.js").is_empty());
+        let auth1_request = &server.requests_to("/v2/api/auth1")[0];
+        assert_eq!(auth1_request.header("X-Radiko-App"), Some("aSmartPhone7a"));
+        let auth2_request = &server.requests_to("/v2/api/auth2")[0];
+        assert_eq!(
+            auth2_request.header("X-Radiko-Partialkey"),
+            Some(general_purpose::STANDARD.encode(&full_key[8..24]).as_str())
+        );
+        assert_eq!(auth2_request.header("X-Radiko-Connection"), Some("wifi"));
+        let location: Vec<f64> = auth2_request
+            .header("X-Radiko-Location")
+            .unwrap()
+            .split(',')
+            .take(2)
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert!((location[0] - 35.689488).abs() < LOCATION_JITTER);
+        assert!((location[1] - 139.691706).abs() < LOCATION_JITTER);
+
+        // auth2で判定されたエリアが指定したエリアと異なる場合はエラー
+        let result = RadikoAuthManager::with_options(
+            RadikoHttpClient::default(),
+            endpoint,
+            AuthOptions {
+                strategy: AuthStrategy::Mobile(MobileAuth::new(Area::Osaka, full_key)),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(RadikoError::AuthFailed(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mobile_auth_config_test() {
+        assert!(matches!(
+            MobileAuth::from_base64_key(Area::Tokyo, "not base64!"),
+            Err(RadikoError::InvalidArgument(_))
+        ));
+
+        let mobile = MobileAuth::from_base64_key(Area::Hokkaido, "AAECAw==").unwrap();
+        assert_eq!(mobile.area_id(), "JP1");
+        assert_eq!(*mobile.full_key, vec![0, 1, 2, 3]);
+        assert!(!format!("{:?}", mobile).contains("[0, 1, 2, 3]"));
+    }
+
+    #[test]
+    fn parse_area_id_test() {
+        let body = r#"document.write('<span class="JP13">TOKYO JAPAN</span>');"#;
+        assert_eq!(RadikoAuthManager::parse_area_id(body).unwrap(), "JP13");
+        let body = r#"document.write('<span class="JP1">HOKKAIDO JAPAN</span>');"#;
+        assert_eq!(RadikoAuthManager::parse_area_id(body).unwrap(), "JP1");
+
+        let body = r#"document.write('<span class="OUT">OUT</span>');"#;
+        assert!(matches!(
+            RadikoAuthManager::parse_area_id(body),
+            Err(RadikoError::OutOfArea(_))
+        ));
+
+        assert!(matches!(
+            RadikoAuthManager::parse_area_id("<html></html>"),
+            Err(RadikoError::AreaDetectionFailed(_))
+        ));
+    }
+
+    #[test]
+    fn parse_auth_key_test() {
+        let script = "var player = new RadikoJSPlayer($audio[0], 'pc_html5', 'bcd151073c03b352e1ef2fd66c32209da9ca0afa', {";
+        assert_eq!(
+            RadikoAuthManager::parse_auth_key(script).unwrap(),
+            "bcd151073c03b352e1ef2fd66c32209da9ca0afa"
+        );
+        assert!(matches!(
+            RadikoAuthManager::parse_auth_key("renamed player"),
+            Err(RadikoError::AuthKeyNotFound)
+        ));
+    }
 }
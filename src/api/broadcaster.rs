@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+
+use crate::error::RadikoError;
+
+use super::{
+    live_audio::{LiveItem, LiveSegment, live_segments},
+    stream::RadikoStream,
+};
+
+/// 購読者ごとに保持するイベント数
+/// 処理がこれ以上遅れた購読者は`StationEvent::Lagged`で取りこぼしを知らされる
+const CHANNEL_CAPACITY: usize = 64;
+
+/// 放送局の購読者に配信されるイベント
+#[derive(Debug, Clone)]
+pub enum StationEvent {
+    Segment(LiveSegment),
+    /// 上流でセグメントが欠落した
+    Gap {
+        after_sequence: u64,
+        missing_segments: u64,
+    },
+    /// 購読者の処理が遅れ、配信済みのイベントを取りこぼした
+    Lagged {
+        skipped_events: u64,
+    },
+    /// 上流の取得に失敗して配信を終了した。以降のイベントは無い
+    Error(Arc<RadikoError>),
+}
+
+/// 放送局ごとに上流のHLSセッションを1つだけ持ち、取得したセグメントを複数の購読者に配信する
+/// 最後の購読者が購読をやめた時点で上流のセッションを終了する
+#[derive(Clone)]
+pub struct StationBroadcaster {
+    inner: Arc<StationBroadcasterRef>,
+}
+
+struct StationBroadcasterRef {
+    stream: RadikoStream,
+    channels: Mutex<HashMap<String, broadcast::Sender<StationEvent>>>,
+}
+
+/// `Radiko::subscribe`で取得する放送局の購読
+/// dropすると購読をやめる
+pub struct StationSubscription {
+    station_id: String,
+    receiver: broadcast::Receiver<StationEvent>,
+}
+
+impl StationSubscription {
+    pub fn station_id(&self) -> &str {
+        &self.station_id
+    }
+
+    /// 次のイベントを待つ。配信が終了した場合はNone
+    pub async fn recv(&mut self) -> Option<StationEvent> {
+        match self.receiver.recv().await {
+            Ok(event) => Some(event),
+            Err(broadcast::error::RecvError::Lagged(skipped_events)) => {
+                Some(StationEvent::Lagged { skipped_events })
+            }
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+}
+
+impl StationBroadcaster {
+    pub(crate) fn new(stream: RadikoStream) -> Self {
+        Self {
+            inner: Arc::new(StationBroadcasterRef {
+                stream,
+                channels: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 放送局を購読する
+    /// 既に購読者がいる場合は上流のセッションを共有し、購読した時点以降のセグメントを受け取る
+    pub fn subscribe(&self, station_id: &str) -> StationSubscription {
+        let mut channels = self.channels();
+        let receiver = match channels.get(station_id) {
+            Some(sender) => sender.subscribe(),
+            None => {
+                let (sender, receiver) = broadcast::channel(CHANNEL_CAPACITY);
+                channels.insert(station_id.to_string(), sender.clone());
+                tokio::spawn(self.clone().run_upstream(station_id.to_string(), sender));
+                receiver
+            }
+        };
+
+        StationSubscription {
+            station_id: station_id.to_string(),
+            receiver,
+        }
+    }
+
+    /// 上流のセッションを持っている放送局
+    pub fn active_stations(&self) -> Vec<String> {
+        let mut station_ids = self.channels().keys().cloned().collect::<Vec<_>>();
+        station_ids.sort();
+        station_ids
+    }
+
+    fn channels(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, broadcast::Sender<StationEvent>>> {
+        self.inner
+            .channels
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    async fn run_upstream(self, station_id: String, sender: broadcast::Sender<StationEvent>) {
+        let segments = live_segments(self.inner.stream.clone(), &station_id);
+        futures_util::pin_mut!(segments);
+        loop {
+            tokio::select! {
+                _ = sender.closed() => {
+                    // 購読の開始と競合した場合は配信を続ける
+                    if self.remove_if_unsubscribed(&station_id, &sender) {
+                        return;
+                    }
+                }
+                item = segments.next() => {
+                    let event = match item {
+                        Some(Ok(LiveItem::Segment(segment))) => StationEvent::Segment(segment),
+                        Some(Ok(LiveItem::Gap {
+                            after_sequence,
+                            missing_segments,
+                        })) => StationEvent::Gap {
+                            after_sequence,
+                            missing_segments,
+                        },
+                        Some(Err(err)) => {
+                            self.remove(&station_id, &sender);
+                            let _ = sender.send(StationEvent::Error(Arc::new(err)));
+                            return;
+                        }
+                        None => {
+                            self.remove(&station_id, &sender);
+                            return;
+                        }
+                    };
+                    let _ = sender.send(event);
+                }
+            }
+        }
+    }
+
+    fn remove_if_unsubscribed(
+        &self,
+        station_id: &str,
+        sender: &broadcast::Sender<StationEvent>,
+    ) -> bool {
+        let mut channels = self.channels();
+        if sender.receiver_count() > 0 {
+            return false;
+        }
+        if channels
+            .get(station_id)
+            .is_some_and(|current| current.same_channel(sender))
+        {
+            channels.remove(station_id);
+        }
+        true
+    }
+
+    fn remove(&self, station_id: &str, sender: &broadcast::Sender<StationEvent>) {
+        let mut channels = self.channels();
+        if channels
+            .get(station_id)
+            .is_some_and(|current| current.same_channel(sender))
+        {
+            channels.remove(station_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        radiko::{EndpointConfig, Radiko},
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_media_playlist},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn broadcast_to_subscribers_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route(
+                "/live/medialist",
+                MockResponse::ok(mock_media_playlist(0, 2, false)),
+            );
+        for sequence in 0..2u8 {
+            server.route(
+                &format!("/live/segments/{}.aac", sequence),
+                MockResponse::ok(vec![sequence]),
+            );
+        }
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+
+        let mut first = radiko.subscribe("TBS").await;
+        let mut second = radiko.subscribe("TBS").await;
+        for subscription in [&mut first, &mut second] {
+            for sequence in 0..2u8 {
+                let Some(StationEvent::Segment(segment)) = subscription.recv().await else {
+                    panic!("segment expected");
+                };
+                assert_eq!(segment.sequence, sequence as u64);
+                assert_eq!(&segment.data[..], [sequence]);
+            }
+        }
+        // 購読者が2人でも上流のセッションは1つ
+        assert_eq!(server.requests_to("/so/playlist.m3u8").len(), 1);
+        assert_eq!(server.requests_to("/live/segments/0.aac").len(), 1);
+
+        drop(first);
+        assert_eq!(radiko.broadcasting_stations().await, vec!["TBS"]);
+        drop(second);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !radiko.broadcasting_stations().await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await?;
+
+        // 購読者がいなくなった後の購読では上流のセッションを開き直す
+        let mut third = radiko.subscribe("TBS").await;
+        assert!(matches!(third.recv().await, Some(StationEvent::Segment(_))));
+        assert_eq!(server.requests_to("/so/playlist.m3u8").len(), 2);
+        Ok(())
+    }
+}
@@ -1,99 +1,365 @@
-const V2_URL: &str = "https://radiko.jp/v2/";
-const V3_URL: &str = "https://radiko.jp/v3/";
-const V4_URL: &str = "https://radiko.jp/v4/";
-const API_URL: &str = "https://api.radiko.jp/";
-const AREA_URL: &str = "https://radiko.jp/area/";
+use std::sync::Arc;
 
-pub struct RadikoEndpoint {}
+use reqwest::Url;
+
+use crate::models::broadcast_day::BroadcastDay;
+
+const RADIKO_URL: &str = "https://radiko.jp";
+const API_URL: &str = "https://api.radiko.jp";
+const LIVE_PLAYLIST_URL: &str = "https://si-f-radiko.smartstream.ne.jp";
+const AREA_FREE_PLAYLIST_URL: &str = "https://si-c-radiko.smartstream.ne.jp";
+const TIMEFREE_PLAYLIST_URL: &str = "https://tf-f-rpaa-radiko.smartstream.ne.jp";
+const PLAYER_SCRIPT_PATH: &str = "apps/js/playerCommon.js";
+
+/// タイムフリーのplaylist.m3u8が1回で返す音声の長さ(秒)
+/// 長時間の番組はseekをこの長さずつ進めて分割して取得する
+pub const TIMEFREE_CHUNK_SECS: i64 = 300;
+
+/// radikoの各ホストの接続先設定
+/// ローカルのモックサーバーに向けたり、radiko側のホスト変更にクレートのリリースを待たずに追従するために利用する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointConfig {
+    /// radiko.jp (認証、ログイン、放送局一覧、番組検索)
+    pub radiko_host: String,
+    /// api.radiko.jp (番組表)
+    pub api_host: String,
+    /// ライブ配信のplaylist.m3u8を返すホスト
+    pub live_playlist_host: String,
+    /// エリアフリー配信のplaylist.m3u8を返すホスト
+    pub area_free_playlist_host: String,
+    /// タイムフリーのplaylist.m3u8を返すホスト
+    pub timefree_playlist_host: String,
+    /// 認証キーを埋め込んでいるプレイヤースクリプトのradiko_hostからのパス
+    pub player_script_path: String,
+}
+
+impl Default for EndpointConfig {
+    fn default() -> Self {
+        Self {
+            radiko_host: RADIKO_URL.to_string(),
+            api_host: API_URL.to_string(),
+            live_playlist_host: LIVE_PLAYLIST_URL.to_string(),
+            area_free_playlist_host: AREA_FREE_PLAYLIST_URL.to_string(),
+            timefree_playlist_host: TIMEFREE_PLAYLIST_URL.to_string(),
+            player_script_path: PLAYER_SCRIPT_PATH.to_string(),
+        }
+    }
+}
+
+impl EndpointConfig {
+    /// 全てのホストを同じURLに向けた設定を作成する
+    /// ex: `EndpointConfig::single_host("http://127.0.0.1:8080")`
+    pub fn single_host(base_url: &str) -> Self {
+        Self {
+            radiko_host: base_url.to_string(),
+            api_host: base_url.to_string(),
+            live_playlist_host: base_url.to_string(),
+            area_free_playlist_host: base_url.to_string(),
+            timefree_playlist_host: base_url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_radiko_host(mut self, host: &str) -> Self {
+        self.radiko_host = host.to_string();
+        self
+    }
+
+    pub fn with_api_host(mut self, host: &str) -> Self {
+        self.api_host = host.to_string();
+        self
+    }
+
+    pub fn with_live_playlist_host(mut self, host: &str) -> Self {
+        self.live_playlist_host = host.to_string();
+        self
+    }
+
+    pub fn with_area_free_playlist_host(mut self, host: &str) -> Self {
+        self.area_free_playlist_host = host.to_string();
+        self
+    }
+
+    pub fn with_timefree_playlist_host(mut self, host: &str) -> Self {
+        self.timefree_playlist_host = host.to_string();
+        self
+    }
+
+    pub fn with_player_script_path(mut self, path: &str) -> Self {
+        self.player_script_path = path.to_string();
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RadikoEndpoint {
+    config: Arc<EndpointConfig>,
+}
 
 impl RadikoEndpoint {
-    pub const RADIKO_HOST: &str = "https://radiko.jp";
+    pub fn new(config: EndpointConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+
+    fn join(host: &str, path: &str) -> String {
+        format!(
+            "{}/{}",
+            host.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    fn radiko_url(&self, path: &str) -> String {
+        Self::join(&self.config.radiko_host, path)
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        Self::join(&self.config.api_host, path)
+    }
+
+    pub fn radiko_host(&self) -> String {
+        self.config.radiko_host.trim_end_matches('/').to_string()
+    }
+
     /// radiko_session取得に利用
-    pub const LOGIN_CHECK_URL: &str = "https://radiko.jp/ap/member/webapi/v2/member/login/check";
+    pub fn login_check_endpoint(&self) -> String {
+        self.radiko_url("ap/member/webapi/v2/member/login/check")
+    }
 
-    pub fn area_id_endpoint() -> String {
-        AREA_URL.to_string()
+    pub fn player_script_endpoint(&self) -> String {
+        self.radiko_url(&self.config.player_script_path)
     }
 
-    pub fn login_endpoint() -> String {
-        format!("{}api/member/login", V4_URL)
+    pub fn area_id_endpoint(&self) -> String {
+        self.radiko_url("area/")
     }
 
-    pub fn auth1_endpoint() -> String {
-        format!("{}api/auth1", V2_URL)
+    pub fn login_endpoint(&self) -> String {
+        self.radiko_url("v4/api/member/login")
     }
 
-    pub fn auth2_endpoint() -> String {
-        format!("{}api/auth2", V2_URL)
+    pub fn logout_endpoint(&self) -> String {
+        self.radiko_url("v4/api/member/logout")
     }
 
-    pub fn search_endpoint() -> String {
-        format!("{}api/program/search", V3_URL)
+    pub fn auth1_endpoint(&self) -> String {
+        self.radiko_url("v2/api/auth1")
     }
 
-    pub fn station_list_from_area_id_endpoint(area_id: &str) -> String {
-        format!("{}station/list/{}.xml", V3_URL, area_id)
+    pub fn auth2_endpoint(&self) -> String {
+        self.radiko_url("v2/api/auth2")
     }
 
-    pub fn station_list_all_endpoint() -> String {
-        format!("{}station/region/full.xml", V3_URL)
+    pub fn search_endpoint(&self) -> String {
+        self.radiko_url("v3/api/program/search")
+    }
+
+    pub fn station_list_from_area_id_endpoint(&self, area_id: &str) -> String {
+        self.radiko_url(&format!("v3/station/list/{}.xml", area_id))
+    }
+
+    pub fn station_list_all_endpoint(&self) -> String {
+        self.radiko_url("v3/station/region/full.xml")
     }
 
     // https://api.radiko.jp/program/v3/now/JP13.xml
-    pub fn now_on_air_programs(area_id: &str) -> String {
-        format!("{}program/v3/now/{}.xml", API_URL, area_id)
+    pub fn now_on_air_programs(&self, area_id: &str) -> String {
+        self.api_url(&format!("program/v3/now/{}.xml", area_id))
+    }
+    pub fn weekly_programs_endpoint(&self, station_id: &str) -> String {
+        self.api_url(&format!("program/v3/weekly/{}.xml", station_id))
+    }
+
+    /// 放送日(5時〜翌5時)ごとのエリアの番組表
+    /// https://api.radiko.jp/program/v3/date/20250622/JP13.xml
+    pub fn date_programs_endpoint(&self, day: BroadcastDay, area_id: &str) -> String {
+        self.api_url(&format!("program/v3/date/{}/{}.xml", day, area_id))
     }
-    pub fn weekly_programs_endpoint(station_id: &str) -> String {
-        format!("{}program/v3/weekly/{}.xml", API_URL, station_id)
+
+    /// 放送日ごとの放送局の番組表
+    /// https://api.radiko.jp/program/v3/date/20250622/station/TBS.xml
+    pub fn station_date_programs_endpoint(&self, day: BroadcastDay, station_id: &str) -> String {
+        self.api_url(&format!(
+            "program/v3/date/{}/station/{}.xml",
+            day, station_id
+        ))
+    }
+
+    /// 今日の放送日のエリアの番組表
+    /// https://api.radiko.jp/program/v3/today/JP13.xml
+    pub fn today_programs_endpoint(&self, area_id: &str) -> String {
+        self.api_url(&format!("program/v3/today/{}.xml", area_id))
     }
 
-    #[allow(dead_code)]
-    pub fn stream_url_list_endpoint(station_id: &str) -> String {
-        format!("{}station/stream/pc_html5/{}.xml", V3_URL, station_id)
+    /// 放送局ごとのplaylist_create_urlの一覧
+    pub fn stream_url_list_endpoint(&self, station_id: &str) -> String {
+        self.radiko_url(&format!("v3/station/stream/pc_html5/{}.xml", station_id))
     }
 
     /// HLSストリーミングのMasterPlaylist.m3u8を返すエンドポイントを取得
+    /// 放送局のストリームXMLが取得できなかった場合に使う
     /// radikoによる仕様変更時にはエンドポイント自体が変更されたり、クエリパラメータが変更される模様
-    pub fn playlist_create_url_endpoint(station_id: &str, lsid: &str) -> String {
-        format!(
-            "https://si-f-radiko.smartstream.ne.jp/so/playlist.m3u8?station_id={}&l=15&lsid={}&type=b",
-            station_id, lsid
+    pub fn playlist_create_url_endpoint(&self, station_id: &str, lsid: &str) -> String {
+        Self::live_playlist_url(
+            &Self::join(&self.config.live_playlist_host, "so/playlist.m3u8"),
+            station_id,
+            lsid,
+            false,
         )
     }
 
-    pub fn area_free_playlist_create_url_endpoint(station_id: &str, lsid: &str) -> String {
-        format!(
-            "https://si-c-radiko.smartstream.ne.jp/so/playlist.m3u8?station_id={}&l=15&lsid={}&type=c",
-            station_id, lsid
+    pub fn area_free_playlist_create_url_endpoint(&self, station_id: &str, lsid: &str) -> String {
+        Self::live_playlist_url(
+            &Self::join(&self.config.area_free_playlist_host, "so/playlist.m3u8"),
+            station_id,
+            lsid,
+            true,
+        )
+    }
+
+    /// ストリームXMLのplaylist_create_urlにライブ配信のクエリパラメータを付与する
+    pub fn live_playlist_url(
+        playlist_create_url: &str,
+        station_id: &str,
+        lsid: &str,
+        area_free: bool,
+    ) -> String {
+        Self::append_query(
+            playlist_create_url,
+            &[
+                ("station_id", station_id),
+                ("l", "15"),
+                ("lsid", lsid),
+                ("type", Self::stream_type(area_free)),
+            ],
         )
     }
+
+    /// タイムフリーのMasterPlaylist.m3u8を返すエンドポイントを取得
+    /// ft/toは番組の開始/終了日時、seekは取得を開始する日時で、いずれもJSTの`%Y%m%d%H%M%S`
+    pub fn timefree_playlist_create_url_endpoint(
+        &self,
+        station_id: &str,
+        ft: &str,
+        to: &str,
+        seek: &str,
+        lsid: &str,
+        area_free: bool,
+    ) -> String {
+        Self::timefree_playlist_url(
+            &Self::join(&self.config.timefree_playlist_host, "tf/playlist.m3u8"),
+            station_id,
+            ft,
+            to,
+            seek,
+            lsid,
+            area_free,
+        )
+    }
+
+    /// ストリームXMLのplaylist_create_urlにタイムフリーのクエリパラメータを付与する
+    pub fn timefree_playlist_url(
+        playlist_create_url: &str,
+        station_id: &str,
+        ft: &str,
+        to: &str,
+        seek: &str,
+        lsid: &str,
+        area_free: bool,
+    ) -> String {
+        Self::append_query(
+            playlist_create_url,
+            &[
+                ("station_id", station_id),
+                ("start_at", ft),
+                ("ft", ft),
+                ("seek", seek),
+                ("end_at", to),
+                ("to", to),
+                ("l", &TIMEFREE_CHUNK_SECS.to_string()),
+                ("lsid", lsid),
+                ("type", Self::stream_type(area_free)),
+            ],
+        )
+    }
+
+    /// ストリームXMLのURLに既にクエリパラメータが含まれていても壊れないように付け足す
+    fn append_query(url: &str, params: &[(&str, &str)]) -> String {
+        match Url::parse(url) {
+            Ok(mut url) => {
+                url.query_pairs_mut().extend_pairs(params);
+                url.to_string()
+            }
+            // 絶対URLとして解釈できない場合は文字列として連結する
+            Err(_) => {
+                let query = params
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>()
+                    .join("&");
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!("{}{}{}", url, separator, query)
+            }
+        }
+    }
+
+    fn stream_type(area_free: bool) -> &'static str {
+        if area_free { "c" } else { "b" }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::api::endpoint::RadikoEndpoint;
+    use crate::{
+        api::endpoint::{EndpointConfig, RadikoEndpoint},
+        models::broadcast_day::BroadcastDay,
+    };
 
     #[test]
     fn area_id_endpoint_test() {
-        let get_area_id_endpoint = RadikoEndpoint::area_id_endpoint();
+        let get_area_id_endpoint = RadikoEndpoint::default().area_id_endpoint();
         assert_eq!(get_area_id_endpoint, "https://radiko.jp/area/");
     }
 
     #[test]
     fn auth1_endpoint_test() {
-        let get_auth1_endpoint = RadikoEndpoint::auth1_endpoint();
+        let get_auth1_endpoint = RadikoEndpoint::default().auth1_endpoint();
         assert_eq!(get_auth1_endpoint, "https://radiko.jp/v2/api/auth1");
     }
 
     #[test]
     fn auth2_endpoint_test() {
-        let get_auth2_endpoint = RadikoEndpoint::auth2_endpoint();
+        let get_auth2_endpoint = RadikoEndpoint::default().auth2_endpoint();
         assert_eq!(get_auth2_endpoint, "https://radiko.jp/v2/api/auth2");
     }
 
+    #[test]
+    fn login_endpoints_test() {
+        let endpoint = RadikoEndpoint::default();
+        assert_eq!(
+            endpoint.login_endpoint(),
+            "https://radiko.jp/v4/api/member/login"
+        );
+        assert_eq!(
+            endpoint.logout_endpoint(),
+            "https://radiko.jp/v4/api/member/logout"
+        );
+        assert_eq!(
+            endpoint.login_check_endpoint(),
+            "https://radiko.jp/ap/member/webapi/v2/member/login/check"
+        );
+        assert_eq!(
+            endpoint.player_script_endpoint(),
+            "https://radiko.jp/apps/js/playerCommon.js"
+        );
+    }
+
     #[test]
     fn stream_url_list_endpoint_test() {
-        let get_stream_url_list_endpoint = RadikoEndpoint::stream_url_list_endpoint("TBS");
+        let get_stream_url_list_endpoint =
+            RadikoEndpoint::default().stream_url_list_endpoint("TBS");
         assert_eq!(
             get_stream_url_list_endpoint,
             "https://radiko.jp/v3/station/stream/pc_html5/TBS.xml"
@@ -104,7 +370,7 @@ mod tests {
     fn search_endpoint_test() {
         assert_eq!(
             "https://radiko.jp/v3/api/program/search",
-            RadikoEndpoint::search_endpoint()
+            RadikoEndpoint::default().search_endpoint()
         );
     }
 
@@ -113,7 +379,7 @@ mod tests {
         let area_id = "JP13";
         assert_eq!(
             format!("https://radiko.jp/v3/station/list/{}.xml", area_id),
-            RadikoEndpoint::station_list_from_area_id_endpoint(area_id)
+            RadikoEndpoint::default().station_list_from_area_id_endpoint(area_id)
         );
     }
 
@@ -121,7 +387,7 @@ mod tests {
     fn stations_list_all_endpoint() {
         assert_eq!(
             "https://radiko.jp/v3/station/region/full.xml",
-            RadikoEndpoint::station_list_all_endpoint()
+            RadikoEndpoint::default().station_list_all_endpoint()
         );
     }
 
@@ -130,7 +396,7 @@ mod tests {
         let area_id = "JP13";
         assert_eq!(
             format!("https://api.radiko.jp/program/v3/now/{}.xml", area_id),
-            RadikoEndpoint::now_on_air_programs(area_id)
+            RadikoEndpoint::default().now_on_air_programs(area_id)
         );
     }
 
@@ -139,7 +405,25 @@ mod tests {
         let station_id = "TBS";
         assert_eq!(
             format!("https://api.radiko.jp/program/v3/weekly/{}.xml", station_id),
-            RadikoEndpoint::weekly_programs_endpoint(station_id)
+            RadikoEndpoint::default().weekly_programs_endpoint(station_id)
+        );
+    }
+
+    #[test]
+    fn date_programs_endpoint_test() {
+        let endpoint = RadikoEndpoint::default();
+        let day: BroadcastDay = "20250622".parse().unwrap();
+        assert_eq!(
+            endpoint.date_programs_endpoint(day, "JP13"),
+            "https://api.radiko.jp/program/v3/date/20250622/JP13.xml"
+        );
+        assert_eq!(
+            endpoint.station_date_programs_endpoint(day, "TBS"),
+            "https://api.radiko.jp/program/v3/date/20250622/station/TBS.xml"
+        );
+        assert_eq!(
+            endpoint.today_programs_endpoint("JP13"),
+            "https://api.radiko.jp/program/v3/today/JP13.xml"
         );
     }
 
@@ -147,7 +431,8 @@ mod tests {
     fn playlist_create_url_endpoint_test() {
         let station_id = "TBS";
         let lsid = crate::utils::generate_md5_hash();
-        let playlist_crate_url = RadikoEndpoint::playlist_create_url_endpoint(station_id, &lsid);
+        let playlist_crate_url =
+            RadikoEndpoint::default().playlist_create_url_endpoint(station_id, &lsid);
         assert_eq!(
             playlist_crate_url,
             format!(
@@ -162,7 +447,7 @@ mod tests {
         let station_id = "MBS";
         let lsid = crate::utils::generate_md5_hash();
         let playlist_crate_url =
-            RadikoEndpoint::area_free_playlist_create_url_endpoint(station_id, &lsid);
+            RadikoEndpoint::default().area_free_playlist_create_url_endpoint(station_id, &lsid);
         assert_eq!(
             playlist_crate_url,
             format!(
@@ -171,4 +456,60 @@ mod tests {
             )
         )
     }
+
+    #[test]
+    fn timefree_playlist_create_url_endpoint_test() {
+        assert_eq!(
+            RadikoEndpoint::default().timefree_playlist_create_url_endpoint(
+                "TBS",
+                "20250701010000",
+                "20250701030000",
+                "20250701011000",
+                "lsid",
+                false
+            ),
+            "https://tf-f-rpaa-radiko.smartstream.ne.jp/tf/playlist.m3u8?station_id=TBS&start_at=20250701010000&ft=20250701010000&seek=20250701011000&end_at=20250701030000&to=20250701030000&l=300&lsid=lsid&type=b"
+        );
+    }
+
+    #[test]
+    fn playlist_url_with_query_test() {
+        assert_eq!(
+            RadikoEndpoint::live_playlist_url(
+                "https://example.com/playlist.m3u8?token=abc",
+                "TBS",
+                "lsid",
+                true
+            ),
+            "https://example.com/playlist.m3u8?token=abc&station_id=TBS&l=15&lsid=lsid&type=c"
+        );
+    }
+
+    #[test]
+    fn overridden_endpoint_config_test() {
+        let endpoint = RadikoEndpoint::new(
+            EndpointConfig::single_host("http://127.0.0.1:8080/")
+                .with_live_playlist_host("http://127.0.0.1:8081")
+                .with_player_script_path("apps/js/newPlayer.js"),
+        );
+
+        assert_eq!(endpoint.radiko_host(), "http://127.0.0.1:8080");
+        assert_eq!(endpoint.area_id_endpoint(), "http://127.0.0.1:8080/area/");
+        assert_eq!(
+            endpoint.player_script_endpoint(),
+            "http://127.0.0.1:8080/apps/js/newPlayer.js"
+        );
+        assert_eq!(
+            endpoint.now_on_air_programs("JP13"),
+            "http://127.0.0.1:8080/program/v3/now/JP13.xml"
+        );
+        assert_eq!(
+            endpoint.playlist_create_url_endpoint("TBS", "lsid"),
+            "http://127.0.0.1:8081/so/playlist.m3u8?station_id=TBS&l=15&lsid=lsid&type=b"
+        );
+        assert_eq!(
+            endpoint.area_free_playlist_create_url_endpoint("MBS", "lsid"),
+            "http://127.0.0.1:8080/so/playlist.m3u8?station_id=MBS&l=15&lsid=lsid&type=c"
+        );
+    }
 }
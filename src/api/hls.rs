@@ -0,0 +1,524 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use hls_m3u8::{MasterPlaylist, MediaPlaylist, tags::VariantStream};
+use reqwest::{StatusCode, Url};
+
+use crate::error::{RadikoError, Result};
+
+use super::{auth::RadikoAuthManager, http::RetryPolicy};
+
+/// radikoのセグメントは`#EXT-X-TARGETDURATION`を僅かに超えることがあるので許容する
+const ALLOWABLE_EXCESS_DURATION: Duration = Duration::from_secs(5);
+
+/// 新しいセグメントが無い場合に再取得するまでの最短の待機時間
+const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// メディアプレイリストに含まれるセグメント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HlsSegment {
+    /// `#EXT-X-MEDIA-SEQUENCE`から数えたセグメントの通し番号
+    pub sequence: u64,
+    /// プレイリストのURLで解決済みの絶対URL
+    pub url: String,
+    pub duration: Duration,
+    /// 放送上の開始日時。`#EXT-X-PROGRAM-DATE-TIME`の値
+    /// ライブ配信のセッションでは、無い場合もプレイリストから推定して埋める
+    pub start_time: Option<DateTime<Utc>>,
+}
+
+/// メディアプレイリストを解析した結果
+#[derive(Debug, Clone)]
+pub struct MediaPlaylistContent {
+    pub target_duration: Duration,
+    pub segments: Vec<HlsSegment>,
+    /// `#EXT-X-ENDLIST`がある場合にtrue
+    pub ended: bool,
+}
+
+impl MediaPlaylistContent {
+    pub fn parse(content: &str, playlist_url: &str) -> Result<Self> {
+        let media_playlist = MediaPlaylist::builder()
+            .allowable_excess_duration(ALLOWABLE_EXCESS_DURATION)
+            .parse(content)
+            .map_err(|err| {
+                RadikoError::PlaylistParse(format!(
+                    "media playlist error: {}, content: {:?}",
+                    err, content
+                ))
+            })?;
+        let segments = media_playlist
+            .segments
+            .values()
+            .enumerate()
+            .map(|(index, segment)| {
+                Ok(HlsSegment {
+                    sequence: (media_playlist.media_sequence + index) as u64,
+                    url: resolve_url(playlist_url, segment.uri())?,
+                    duration: segment.duration.duration(),
+                    start_time: segment.program_date_time.as_ref().and_then(|date_time| {
+                        DateTime::parse_from_rfc3339(&date_time.date_time)
+                            .ok()
+                            .map(|date_time| date_time.with_timezone(&Utc))
+                    }),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            target_duration: media_playlist.target_duration,
+            segments,
+            ended: media_playlist.has_end_list,
+        })
+    }
+}
+
+/// マスタープレイリストから最初のメディアプレイリストのURIを取り出す
+pub fn extract_medialist_url(master_playlist_content: &str) -> Result<String> {
+    let master_playlist = MasterPlaylist::try_from(master_playlist_content).map_err(|err| {
+        RadikoError::PlaylistParse(format!(
+            "extract_medialist_url error: {}, master_playlist_content: {:?}",
+            err, master_playlist_content
+        ))
+    })?;
+    master_playlist
+        .variant_streams
+        .first()
+        .ok_or_else(|| RadikoError::PlaylistParse("No stream found in master playlist".to_string()))
+        .and_then(|stream| match stream {
+            VariantStream::ExtXStreamInf { uri, .. } => Ok(uri.to_string()),
+            _ => Err(RadikoError::PlaylistParse(
+                "Invalid stream type".to_string(),
+            )),
+        })
+}
+
+/// プレイリスト内の相対URLを絶対URLにする
+pub fn resolve_url(base_url: &str, uri: &str) -> Result<String> {
+    Url::parse(base_url)
+        .and_then(|base_url| base_url.join(uri))
+        .map(String::from)
+        .map_err(|err| {
+            RadikoError::PlaylistParse(format!(
+                "invalid uri: {}, base_url: {}, error: {}",
+                uri, base_url, err
+            ))
+        })
+}
+
+/// セッション中に行った再試行の回数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HlsSessionStats {
+    /// セグメントのダウンロードを再試行した回数
+    pub segment_retries: u32,
+    /// メディアプレイリストが404になり、マスタープレイリストを取得し直した回数
+    pub master_playlist_reloads: u32,
+    /// プレイリストの取得に失敗し、マスタープレイリストから接続し直した回数
+    pub reconnects: u32,
+}
+
+/// ライブ配信のメディアプレイリストをポーリングしてセグメントを順に返す
+/// プレイリストを再取得した際に既に返したセグメントはメディアシーケンス番号で除外する
+/// プレイリストの取得に失敗した場合は、再接続の方針に従ってマスタープレイリストから接続し直す
+pub struct HlsLiveSession {
+    auth_manager: Arc<RadikoAuthManager>,
+    master_playlist_url: Option<String>,
+    media_playlist_url: String,
+    segment_retry: RetryPolicy,
+    reconnect: RetryPolicy,
+    /// 連続して再接続した回数。プレイリストを取得できたら0に戻す
+    reconnect_attempts: u32,
+    last_sequence: Option<u64>,
+    /// マスタープレイリストからメディアプレイリストのURLを取得し直した後、最初のプレイリストをまだ取得していない
+    reloaded: bool,
+    /// 放送上の開始日時を推定する基準のセグメントのシーケンス番号と開始日時
+    time_anchor: Option<(u64, DateTime<Utc>)>,
+    pending: VecDeque<HlsSegment>,
+    poll_interval: Duration,
+    ended: bool,
+    stats: HlsSessionStats,
+}
+
+impl HlsLiveSession {
+    pub fn new(auth_manager: Arc<RadikoAuthManager>, media_playlist_url: String) -> Self {
+        Self {
+            auth_manager,
+            master_playlist_url: None,
+            media_playlist_url,
+            segment_retry: RetryPolicy::none(),
+            reconnect: RetryPolicy::none(),
+            reconnect_attempts: 0,
+            last_sequence: None,
+            reloaded: false,
+            time_anchor: None,
+            pending: VecDeque::new(),
+            poll_interval: MIN_POLL_INTERVAL,
+            ended: false,
+            stats: HlsSessionStats::default(),
+        }
+    }
+
+    /// メディアプレイリストが404になった場合に取得し直すマスタープレイリスト
+    /// メディアプレイリストのURLに含まれるセッションが失効した場合に、新しいURLで再開できる
+    pub fn with_master_playlist_url(mut self, master_playlist_url: String) -> Self {
+        self.master_playlist_url = Some(master_playlist_url);
+        self
+    }
+
+    /// セグメントのダウンロードの再試行方針
+    /// HTTPクライアントの再試行とは別に、404や本文の受信中の切断を含む全てのエラーで再試行する
+    pub fn with_segment_retry(mut self, segment_retry: RetryPolicy) -> Self {
+        self.segment_retry = segment_retry;
+        self
+    }
+
+    /// プレイリストの取得に失敗した場合に再接続する方針
+    /// マスタープレイリストが無い場合は再接続しない
+    pub fn with_reconnect(mut self, reconnect: RetryPolicy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    pub fn stats(&self) -> HlsSessionStats {
+        self.stats
+    }
+
+    /// 次のセグメントを返す
+    /// 新しいセグメントが公開されるまではプレイリストの再取得を繰り返し、配信が終了した場合はNoneを返す
+    /// 再接続を諦めた場合は最後のエラーを返す
+    pub async fn next_segment(&mut self) -> Result<Option<HlsSegment>> {
+        loop {
+            if let Some(segment) = self.pending.pop_front() {
+                self.last_sequence = Some(segment.sequence);
+                return Ok(Some(segment));
+            }
+            if self.ended {
+                return Ok(None);
+            }
+            match self.refresh().await {
+                Ok(has_new_segments) => {
+                    self.reconnect_attempts = 0;
+                    if !has_new_segments && !self.ended {
+                        tokio::time::sleep(self.poll_interval).await;
+                    }
+                }
+                Err(err) => self.reconnect_after(err).await?,
+            }
+        }
+    }
+
+    /// 指数バックオフで待機してから、マスタープレイリストからメディアプレイリストのURLを取得し直す
+    /// 再接続の回数が上限に達した場合は最後のエラーを返す
+    async fn reconnect_after(&mut self, mut err: RadikoError) -> Result<()> {
+        loop {
+            if self.master_playlist_url.is_none()
+                || self.reconnect_attempts >= self.reconnect.max_retries
+            {
+                return Err(err);
+            }
+            tokio::time::sleep(self.reconnect.backoff(self.reconnect_attempts)).await;
+            self.reconnect_attempts += 1;
+            self.stats.reconnects += 1;
+            match self.reload_media_playlist_url().await {
+                Ok(()) => return Ok(()),
+                Err(reload_err) => err = reload_err,
+            }
+        }
+    }
+
+    /// セグメントを認証ヘッダー付きでダウンロードする
+    /// 失敗した場合は再試行方針に従って指数バックオフで再試行する
+    pub async fn download(&mut self, segment: &HlsSegment) -> Result<Vec<u8>> {
+        let mut attempt = 0;
+        loop {
+            match self.try_download(segment).await {
+                Ok(data) => return Ok(data),
+                Err(err) if attempt >= self.segment_retry.max_retries => return Err(err),
+                Err(_) => {}
+            }
+            tokio::time::sleep(self.segment_retry.backoff(attempt)).await;
+            attempt += 1;
+            self.stats.segment_retries += 1;
+        }
+    }
+
+    async fn try_download(&self, segment: &HlsSegment) -> Result<Vec<u8>> {
+        Ok(self
+            .auth_manager
+            .send_authed(&segment.url)
+            .await?
+            .bytes()
+            .await?
+            .to_vec())
+    }
+
+    /// メディアプレイリストを取得する
+    /// 404の場合はマスタープレイリストから新しいメディアプレイリストのURLを取得し直す
+    async fn fetch_media_playlist(&mut self) -> Result<String> {
+        let err = match self
+            .auth_manager
+            .send_authed(&self.media_playlist_url)
+            .await
+        {
+            Ok(response) => return Ok(response.text().await?),
+            Err(err) => err,
+        };
+        if self.master_playlist_url.is_none()
+            || !matches!(&err, RadikoError::HttpStatus { status, .. } if *status == StatusCode::NOT_FOUND)
+        {
+            return Err(err);
+        }
+
+        self.reload_media_playlist_url().await?;
+        self.stats.master_playlist_reloads += 1;
+        Ok(self
+            .auth_manager
+            .send_authed(&self.media_playlist_url)
+            .await?
+            .text()
+            .await?)
+    }
+
+    /// マスタープレイリストからメディアプレイリストのURLを取得し直す
+    async fn reload_media_playlist_url(&mut self) -> Result<()> {
+        let Some(master_playlist_url) = &self.master_playlist_url else {
+            return Ok(());
+        };
+        let master_playlist_content = self
+            .auth_manager
+            .send_authed(master_playlist_url)
+            .await?
+            .text()
+            .await?;
+        self.media_playlist_url = resolve_url(
+            master_playlist_url,
+            &extract_medialist_url(&master_playlist_content)?,
+        )?;
+        self.reloaded = true;
+        Ok(())
+    }
+
+    /// プレイリストを再取得し、新しいセグメントがあればtrueを返す
+    async fn refresh(&mut self) -> Result<bool> {
+        let content = self.fetch_media_playlist().await?;
+        let fetched_at = Utc::now();
+        let mut playlist = MediaPlaylistContent::parse(&content, &self.media_playlist_url)?;
+        self.poll_interval = (playlist.target_duration / 2).max(MIN_POLL_INTERVAL);
+        self.ended = playlist.ended;
+
+        // 新しいメディアプレイリストでシーケンス番号が振り直された場合は、既に返した番号で除外しない
+        if std::mem::take(&mut self.reloaded)
+            && let (Some(last), Some(newest)) = (self.last_sequence, playlist.segments.last())
+            && newest.sequence < last
+        {
+            self.last_sequence = None;
+            self.time_anchor = None;
+        }
+        self.estimate_start_times(&mut playlist, fetched_at);
+        let last_sequence = self.last_sequence;
+        self.pending.extend(
+            playlist
+                .segments
+                .into_iter()
+                .filter(|segment| last_sequence.is_none_or(|last| segment.sequence > last)),
+        );
+
+        Ok(!self.pending.is_empty())
+    }
+
+    /// `#EXT-X-PROGRAM-DATE-TIME`が無いセグメントの放送上の開始日時を推定する
+    /// 最初に取得したプレイリストの最新のセグメントが取得時点で終わったものとし、
+    /// 以降はシーケンス番号の差と`#EXT-X-TARGETDURATION`から求める
+    fn estimate_start_times(
+        &mut self,
+        playlist: &mut MediaPlaylistContent,
+        fetched_at: DateTime<Utc>,
+    ) {
+        let Some(newest) = playlist.segments.last() else {
+            return;
+        };
+        let (anchor_sequence, anchor_time) = *self.time_anchor.get_or_insert_with(|| {
+            let start_time = newest.start_time.unwrap_or_else(|| {
+                fetched_at - TimeDelta::from_std(newest.duration).unwrap_or_default()
+            });
+            (newest.sequence, start_time)
+        });
+        let target_duration = TimeDelta::from_std(playlist.target_duration).unwrap_or_default();
+        for segment in &mut playlist.segments {
+            if segment.start_time.is_none() {
+                let offset = segment.sequence as i64 - anchor_sequence as i64;
+                segment.start_time = Some(anchor_time + target_duration * offset as i32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::{endpoint::EndpointConfig, endpoint::RadikoEndpoint, http::RadikoHttpClient},
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_media_playlist},
+    };
+
+    #[test]
+    fn parse_media_playlist_test() -> anyhow::Result<()> {
+        let playlist = MediaPlaylistContent::parse(
+            &mock_media_playlist(100, 2, false),
+            "https://example.com/live/medialist?session=abc",
+        )?;
+
+        assert_eq!(playlist.target_duration, Duration::from_secs(5));
+        assert!(!playlist.ended);
+        assert_eq!(
+            playlist.segments,
+            vec![
+                HlsSegment {
+                    sequence: 100,
+                    url: "https://example.com/live/segments/100.aac".to_string(),
+                    duration: Duration::from_secs(5),
+                    start_time: None,
+                },
+                HlsSegment {
+                    sequence: 101,
+                    url: "https://example.com/live/segments/101.aac".to_string(),
+                    duration: Duration::from_secs(5),
+                    start_time: None,
+                },
+            ]
+        );
+
+        let playlist = MediaPlaylistContent::parse(
+            "#EXTM3U\n#EXT-X-TARGETDURATION:5\n#EXT-X-MEDIA-SEQUENCE:1\n#EXT-X-PROGRAM-DATE-TIME:2025-06-28T20:00:00+09:00\n#EXTINF:5,\n1.aac\n",
+            "https://example.com/live/medialist",
+        )?;
+        assert_eq!(
+            playlist.segments[0].start_time,
+            Some("2025-06-28T11:00:00Z".parse()?)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn live_session_dedupe_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/medialist",
+                MockResponse::ok(mock_media_playlist(10, 2, false)),
+            )
+            .route(
+                "/medialist",
+                MockResponse::ok(mock_media_playlist(11, 2, true)),
+            );
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let auth_manager =
+            Arc::new(RadikoAuthManager::new(RadikoHttpClient::default(), endpoint).await?);
+        let mut session =
+            HlsLiveSession::new(auth_manager, format!("{}/medialist", server.base_url()));
+
+        let mut sequences = Vec::new();
+        while let Some(segment) = session.next_segment().await? {
+            sequences.push(segment.sequence);
+        }
+
+        assert_eq!(sequences, vec![10, 11, 12]);
+        assert_eq!(server.requests_to("/medialist").len(), 2);
+        Ok(())
+    }
+
+    async fn live_session(server: &MockServer) -> anyhow::Result<HlsLiveSession> {
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let auth_manager =
+            Arc::new(RadikoAuthManager::new(RadikoHttpClient::default(), endpoint).await?);
+        Ok(
+            HlsLiveSession::new(auth_manager, format!("{}/medialist", server.base_url()))
+                .with_master_playlist_url(format!("{}/master.m3u8", server.base_url()))
+                .with_reconnect(RetryPolicy {
+                    max_retries: 2,
+                    initial_backoff: Duration::from_millis(1),
+                    max_backoff: Duration::from_millis(1),
+                }),
+        )
+    }
+
+    #[tokio::test]
+    async fn live_session_sequence_reset_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/master.m3u8",
+                MockResponse::ok(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\nmedialist2\n",
+                ),
+            )
+            .route(
+                "/medialist",
+                MockResponse::ok(mock_media_playlist(100, 2, false)),
+            )
+            .route("/medialist", MockResponse::status(404))
+            .route(
+                "/medialist2",
+                MockResponse::ok(mock_media_playlist(0, 2, true)),
+            );
+        let mut session = live_session(&server).await?;
+
+        let mut sequences = Vec::new();
+        while let Some(segment) = session.next_segment().await? {
+            sequences.push(segment.sequence);
+        }
+
+        // 新しいメディアプレイリストでシーケンス番号が振り直されても除外しない
+        assert_eq!(sequences, vec![100, 101, 0, 1]);
+        assert_eq!(session.stats().master_playlist_reloads, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn live_session_reconnect_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/master.m3u8",
+                MockResponse::ok(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\nmedialist2\n",
+                ),
+            )
+            .route(
+                "/medialist",
+                MockResponse::ok(mock_media_playlist(10, 2, false)),
+            )
+            .route("/medialist", MockResponse::ok("broken"))
+            .route(
+                "/medialist2",
+                MockResponse::ok(mock_media_playlist(11, 2, true)),
+            );
+        let mut session = live_session(&server).await?;
+
+        let mut sequences = Vec::new();
+        while let Some(segment) = session.next_segment().await? {
+            sequences.push(segment.sequence);
+        }
+
+        assert_eq!(sequences, vec![10, 11, 12]);
+        assert_eq!(session.stats().reconnects, 1);
+
+        // 再接続し続けても取得できない場合は最後のエラーを返す
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route("/master.m3u8", MockResponse::status(404))
+            .route("/medialist", MockResponse::ok("broken"));
+        let mut session = live_session(&server).await?;
+        assert!(matches!(
+            session.next_segment().await,
+            Err(RadikoError::HttpStatus { .. })
+        ));
+        assert_eq!(session.stats().reconnects, 2);
+        assert_eq!(server.requests_to("/master.m3u8").len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+
+use crate::{error::Result, utils::ensure_success};
+
+/// 通信に失敗した場合の再試行方針
+/// 接続エラー、タイムアウト、5xx、429の場合に指数バックオフで再試行する
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 初回リクエストに加えて再試行する最大回数
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 再試行しない
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    /// ライブ配信のセグメントの再試行の既定値
+    /// セグメントは5秒ごとに公開されるので、次のセグメントが公開される前後まで再試行する
+    pub fn segment() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+
+    /// ライブ配信のプレイリストの取得に失敗した場合の再接続の既定値
+    pub fn reconnect() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+
+    /// attempt回目(0始まり)の再試行前に待機する時間
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+
+    fn is_retryable_status(status: StatusCode) -> bool {
+        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn is_retryable_error(error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect() || error.is_request()
+    }
+}
+
+/// 全APIで共有するHTTPクライアント
+#[derive(Debug, Clone, Default)]
+pub struct RadikoHttpClient {
+    client: Client,
+    retry_policy: RetryPolicy,
+}
+
+impl RadikoHttpClient {
+    pub fn new(client: Client, retry_policy: RetryPolicy) -> Self {
+        Self {
+            client,
+            retry_policy,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// 再試行方針に従ってリクエストを送信する
+    /// ステータスコードの判定は呼び出し側で行う
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let mut attempt = 0;
+        loop {
+            let Some(retry_request) = request.try_clone() else {
+                return Ok(request.send().await?);
+            };
+            let can_retry = attempt < self.retry_policy.max_retries;
+            match retry_request.send().await {
+                Ok(response)
+                    if can_retry && RetryPolicy::is_retryable_status(response.status()) => {}
+                Ok(response) => return Ok(response),
+                Err(err) if can_retry && RetryPolicy::is_retryable_error(&err) => {}
+                Err(err) => return Err(err.into()),
+            }
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// 成功ステータスであることを確認してレスポンスボディを文字列で返す
+    pub async fn text(&self, request: RequestBuilder) -> Result<String> {
+        Ok(ensure_success(self.send(request).await?)
+            .await?
+            .text()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::RadikoError,
+        test_server::{MockResponse, MockServer},
+    };
+
+    #[test]
+    fn backoff_test() {
+        let retry_policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+        };
+
+        assert_eq!(retry_policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(retry_policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(retry_policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(retry_policy.backoff(30), Duration::from_millis(350));
+    }
+
+    #[tokio::test]
+    async fn retry_server_error_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        server
+            .route("/flaky", MockResponse::status(503))
+            .route("/flaky", MockResponse::ok("recovered"));
+        let http = RadikoHttpClient::new(
+            Client::new(),
+            RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            },
+        );
+
+        let body = http
+            .text(http.get(&format!("{}/flaky", server.base_url())))
+            .await?;
+
+        assert_eq!(body, "recovered");
+        assert_eq!(server.requests_to("/flaky").len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_retry_client_error_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        server.route("/missing", MockResponse::status(404));
+        let http = RadikoHttpClient::default();
+
+        let result = http
+            .text(http.get(&format!("{}/missing", server.base_url())))
+            .await;
+
+        assert!(matches!(result, Err(RadikoError::HttpStatus { .. })));
+        assert_eq!(server.requests_to("/missing").len(), 1);
+        Ok(())
+    }
+}
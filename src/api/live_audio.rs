@@ -0,0 +1,256 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+
+use crate::{error::Result, recorder::adts::strip_id3};
+
+use super::{hls::HlsLiveSession, stream::RadikoStream};
+
+/// ライブ配信から取得したセグメント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveSegment {
+    /// メディアシーケンス番号
+    pub sequence: u64,
+    pub duration: Duration,
+    /// ID3タグを取り除いたADTS形式のAAC
+    pub data: Bytes,
+}
+
+/// `Radiko::live_audio`が返すストリームの要素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LiveAudio {
+    /// ID3タグを取り除いたADTS形式のAAC
+    Data(Bytes),
+    /// セグメントが欠落した。ストリームはその後も続く
+    Gap {
+        after_sequence: u64,
+        missing_segments: u64,
+    },
+}
+
+/// `live_segments`が返すストリームの要素
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LiveItem {
+    Segment(LiveSegment),
+    Gap {
+        after_sequence: u64,
+        missing_segments: u64,
+    },
+}
+
+/// ライブ配信のセグメントを順に返すストリームの状態
+/// 呼び出し側がポーリングした時にだけ次のセグメントを取得するので、消費が遅い場合も取得が先行することはない
+struct LiveSegments {
+    stream: RadikoStream,
+    station_id: String,
+    session: Option<HlsLiveSession>,
+    /// 最後に返したセグメント、または欠落として知らせたセグメントのシーケンス番号
+    last_sequence: Option<u64>,
+    pending: VecDeque<LiveItem>,
+    finished: bool,
+}
+
+impl LiveSegments {
+    async fn next(&mut self) -> Option<Result<LiveItem>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.finished {
+                return None;
+            }
+
+            let session = match self.session.as_mut() {
+                Some(session) => session,
+                None => match self.stream.live_session(&self.station_id).await {
+                    Ok(session) => self.session.insert(session),
+                    Err(err) => {
+                        self.finished = true;
+                        return Some(Err(err));
+                    }
+                },
+            };
+
+            // 再接続はセッションの中で行うので、エラーは再接続を諦めた場合だけ
+            match session.next_segment().await {
+                Ok(Some(segment)) => {
+                    let after_sequence = self
+                        .last_sequence
+                        .unwrap_or(segment.sequence.saturating_sub(1));
+                    // 再接続の間にプレイリストから消えたセグメント
+                    let skipped = segment.sequence.saturating_sub(after_sequence + 1);
+                    self.last_sequence = Some(segment.sequence);
+                    match session.download(&segment).await {
+                        Ok(data) => {
+                            if skipped > 0 {
+                                self.pending.push_back(LiveItem::Gap {
+                                    after_sequence,
+                                    missing_segments: skipped,
+                                });
+                            }
+                            self.pending.push_back(LiveItem::Segment(LiveSegment {
+                                sequence: segment.sequence,
+                                duration: segment.duration,
+                                data: Bytes::copy_from_slice(strip_id3(&data)),
+                            }));
+                        }
+                        // 取得に失敗したセグメントは、後続のセグメントを待たずにその場で欠落として知らせる
+                        Err(_) => self.pending.push_back(LiveItem::Gap {
+                            after_sequence,
+                            missing_segments: skipped + 1,
+                        }),
+                    }
+                }
+                // 配信が終了した
+                Ok(None) => self.finished = true,
+                Err(err) => {
+                    self.finished = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// ライブ配信のセグメントを到着順に返すストリーム
+/// プレイリストのポーリング、認証トークンの更新、再接続は内部で行う
+/// 取得できなかったセグメントやプレイリストから消えたセグメントは`LiveItem::Gap`で知らせ、ストリームはその後も続く
+pub(crate) fn live_segments(
+    stream: RadikoStream,
+    station_id: &str,
+) -> impl Stream<Item = Result<LiveItem>> + Send + use<> {
+    let live_segments = LiveSegments {
+        stream,
+        station_id: station_id.to_string(),
+        session: None,
+        last_sequence: None,
+        pending: VecDeque::new(),
+        finished: false,
+    };
+    futures_util::stream::unfold(live_segments, |mut live_segments| async move {
+        let item = live_segments.next().await?;
+        Some((item, live_segments))
+    })
+}
+
+/// ライブ配信のAACを到着順に返すストリーム
+pub fn live_audio(
+    stream: RadikoStream,
+    station_id: &str,
+) -> impl Stream<Item = Result<LiveAudio>> + Send + use<> {
+    live_segments(stream, station_id).map(|item| {
+        item.map(|item| match item {
+            LiveItem::Segment(segment) => LiveAudio::Data(segment.data),
+            LiveItem::Gap {
+                after_sequence,
+                missing_segments,
+            } => LiveAudio::Gap {
+                after_sequence,
+                missing_segments,
+            },
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        radiko::{EndpointConfig, Radiko, RetryPolicy},
+        recorder::adts::tests::id3_tag,
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_media_playlist},
+    };
+
+    use super::*;
+
+    async fn collect_items(radiko: &Radiko) -> Vec<String> {
+        radiko
+            .live_audio("TBS")
+            .await
+            .map(|item| match item {
+                Ok(LiveAudio::Data(bytes)) => format!("{:?}", bytes.to_vec()),
+                Ok(LiveAudio::Gap {
+                    after_sequence,
+                    missing_segments,
+                }) => format!("gap {} {}", after_sequence, missing_segments),
+                Err(err) => err.to_string(),
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn live_audio_gap_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route(
+                "/live/medialist",
+                MockResponse::ok(mock_media_playlist(0, 3, false)),
+            )
+            .route(
+                "/live/medialist",
+                MockResponse::ok(mock_media_playlist(5, 2, true)),
+            );
+        for sequence in [0u8, 2, 5, 6] {
+            let mut segment = id3_tag(b"timestamp");
+            segment.push(sequence);
+            server.route(
+                &format!("/live/segments/{}.aac", sequence),
+                MockResponse::ok(segment),
+            );
+        }
+        let radiko = Radiko::builder()
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .segment_retry_policy(RetryPolicy::none())
+            .build()
+            .await?;
+
+        let items = collect_items(&radiko).await;
+
+        assert_eq!(
+            items,
+            vec!["[0]", "gap 0 1", "[2]", "gap 2 2", "[5]", "[6]"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn live_audio_trailing_gap_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route(
+                "/live/medialist",
+                MockResponse::ok(mock_media_playlist(0, 3, true)),
+            )
+            .route("/live/segments/0.aac", MockResponse::ok(vec![0]))
+            .route("/live/segments/1.aac", MockResponse::status(404))
+            .route("/live/segments/2.aac", MockResponse::status(404));
+        let radiko = Radiko::builder()
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .segment_retry_policy(RetryPolicy::none())
+            .build()
+            .await?;
+
+        let items = collect_items(&radiko).await;
+
+        // 最後のセグメントが取得できなくても、ストリームの終了前に欠落を知らせる
+        assert_eq!(items, vec!["[0]", "gap 0 1", "gap 1 1"]);
+        Ok(())
+    }
+}
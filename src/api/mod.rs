@@ -1,5 +1,10 @@
 pub(crate) mod auth;
+pub(crate) mod broadcaster;
 pub(crate) mod endpoint;
+pub(crate) mod hls;
+pub(crate) mod http;
+pub(crate) mod live_audio;
 pub(crate) mod program;
+pub(crate) mod session_store;
 pub(crate) mod station;
 pub(crate) mod stream;
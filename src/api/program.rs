@@ -1,87 +1,182 @@
 use std::sync::Arc;
 
-use crate::models::search::SearchCondition;
-use crate::{dto::program_xml::RadikoProgramXml, models::program::Programs};
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use futures_util::Stream;
 
-use super::endpoint::RadikoEndpoint;
+use crate::error::{RadikoError, Result};
+use crate::models::search::{SearchCondition, SearchResult};
+use crate::{
+    dto::{program_xml::RadikoProgramXml, search_json::SearchResultJson},
+    models::{broadcast_day::BroadcastDay, program::Programs},
+};
 
+use super::{endpoint::RadikoEndpoint, http::RadikoHttpClient};
+
+#[derive(Clone)]
 pub struct RadikoProgram {
     inner: Arc<RadikoProgramRef>,
 }
 
 struct RadikoProgramRef {
-    client: Client,
+    http: RadikoHttpClient,
+    endpoint: RadikoEndpoint,
 }
 
 impl RadikoProgram {
-    pub fn new() -> Self {
+    pub fn new(http: RadikoHttpClient, endpoint: RadikoEndpoint) -> Self {
         Self {
-            inner: Arc::new(RadikoProgramRef {
-                client: Client::new(),
-            }),
+            inner: Arc::new(RadikoProgramRef { http, endpoint }),
         }
     }
 
     pub async fn now_on_air_programs(&self, area_id: &str) -> Result<Programs> {
-        let res = self
-            .inner
-            .client
-            .get(RadikoEndpoint::now_on_air_programs(area_id))
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        let radiko_program: RadikoProgramXml = quick_xml::de::from_str(&res)?;
-
-        Ok(Programs::from(radiko_program))
+        self.program_xml(&self.inner.endpoint.now_on_air_programs(area_id))
+            .await
     }
 
-    pub async fn find_program(&self, condition: &SearchCondition) -> Result<Programs> {
+    pub async fn find_program(&self, condition: &SearchCondition) -> Result<SearchResult> {
         if condition.key.is_empty() {
-            return Err(anyhow!("condition key required."));
+            return Err(RadikoError::InvalidArgument(
+                "condition key required.".to_string(),
+            ));
         }
 
         let res = self
             .inner
-            .client
-            .get(RadikoEndpoint::search_endpoint())
-            .query(&condition.to_query_params())
-            .send()
-            .await?
-            .text()
+            .http
+            .text(
+                self.inner
+                    .http
+                    .get(&self.inner.endpoint.search_endpoint())
+                    .query(&condition.to_query_params()),
+            )
             .await?;
 
-        Ok(serde_json::from_str(&res)?)
+        let search_result: SearchResultJson = serde_json::from_str(&res)?;
+
+        SearchResult::try_from(search_result)
+    }
+
+    /// `page_idx`のページから最後のページまで順に検索する
+    /// エラーが発生した場合はそれを返して終了する
+    pub fn search_pages(
+        &self,
+        condition: &SearchCondition,
+    ) -> impl Stream<Item = Result<SearchResult>> + Send + use<> {
+        let state = Some((self.clone(), condition.clone(), None));
+        futures_util::stream::unfold(state, |state| async move {
+            let (program, mut condition, page_count) = state?;
+            match program.find_program(&condition).await {
+                Ok(result) => {
+                    // 全体のページ数は最初のページの件数で決め、それ以降のページは取得しない
+                    let page_count = page_count.or_else(|| result.page_count(&condition));
+                    let page_idx = condition.page_idx.unwrap_or_default() + 1;
+                    let next = (result.has_next_page(&condition)
+                        && page_count.is_some_and(|page_count| page_idx < page_count))
+                    .then(|| {
+                        condition.page_idx = Some(page_idx);
+                        (program, condition, page_count)
+                    });
+                    Some((Ok(result), next))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        })
     }
 
     pub async fn weekly_programs_from_station(&self, station_id: &str) -> Result<Programs> {
-        let res = self
-            .inner
-            .client
-            .get(RadikoEndpoint::weekly_programs_endpoint(station_id))
-            .send()
-            .await?
-            .text()
-            .await?;
+        self.program_xml(&self.inner.endpoint.weekly_programs_endpoint(station_id))
+            .await
+    }
+
+    pub async fn programs_by_date(&self, area_id: &str, day: BroadcastDay) -> Result<Programs> {
+        self.program_xml(&self.inner.endpoint.date_programs_endpoint(day, area_id))
+            .await
+    }
+
+    pub async fn programs_by_station_date(
+        &self,
+        station_id: &str,
+        day: BroadcastDay,
+    ) -> Result<Programs> {
+        self.program_xml(
+            &self
+                .inner
+                .endpoint
+                .station_date_programs_endpoint(day, station_id),
+        )
+        .await
+    }
+
+    pub async fn today_programs(&self, area_id: &str) -> Result<Programs> {
+        self.program_xml(&self.inner.endpoint.today_programs_endpoint(area_id))
+            .await
+    }
+
+    /// 番組表XMLを取得する
+    async fn program_xml(&self, url: &str) -> Result<Programs> {
+        let res = self.inner.http.text(self.inner.http.get(url)).await?;
 
         let radiko_program: RadikoProgramXml = quick_xml::de::from_str(&res)?;
 
-        Ok(Programs::from(radiko_program))
+        Programs::try_from(radiko_program)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::radiko::Radiko;
+    use crate::{
+        models::{area::Area, broadcast_day::BroadcastDay},
+        radiko::{EndpointConfig, Radiko},
+        test_server::{MockResponse, MockServer, mock_auth_routes},
+    };
+    use anyhow::Result;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn programs_by_date_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/program/v3/date/20250622/JP13.xml",
+                MockResponse::ok(include_str!("../../examples/radiko/TBS.xml")),
+            )
+            .route(
+                "/program/v3/date/20250622/station/TBS.xml",
+                MockResponse::ok(include_str!("../../examples/radiko/TBS.xml")),
+            )
+            .route(
+                "/program/v3/today/JP13.xml",
+                MockResponse::ok(include_str!("../../examples/radiko/TBS.xml")),
+            );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let date = NaiveDate::from_ymd_opt(2025, 6, 22).unwrap();
+
+        let programs = radiko.programs_by_date(Area::Tokyo, date).await?;
+        assert_eq!(programs.data[0].station_id, "TBS");
+        assert_eq!(programs.data[0].program_date, Some(date));
+        assert!(
+            !radiko
+                .programs_by_station_date("TBS", date)
+                .await?
+                .data
+                .is_empty()
+        );
+        assert!(!radiko.today_programs("JP13").await?.data.is_empty());
+
+        let at = BroadcastDay::new(date).parse_hhmm("2500")?;
+        let program = radiko.program_at("TBS", at).await?.unwrap();
+        assert_eq!(program.program_id.as_deref(), Some("11786350"));
+        Ok(())
+    }
 
     #[tokio::test]
     async fn get_now_on_air_programs_test() -> Result<()> {
         let area_id = "JP13";
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let programs = radiko.now_on_air_programs(area_id).await?;
 
         println!("{}_now_on_air_programs: {:#?}", area_id, programs);
@@ -91,6 +186,76 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn find_all_programs_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let search_result: serde_json::Value =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+        let page = |page_idx: usize| {
+            let mut page = search_result.clone();
+            page["meta"]["page_idx"] = page_idx.into();
+            let data = page["data"].as_array().unwrap();
+            page["data"] = data.iter().skip(page_idx * 5).take(5).cloned().collect();
+            MockResponse::ok(page.to_string())
+        };
+        server
+            .route("/v3/api/program/search", page(0))
+            .route("/v3/api/program/search", page(1));
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let search_condition = SearchCondition {
+            key: vec!["トム・ブラウン".to_string()],
+            row_limit: Some(5),
+            ..Default::default()
+        };
+
+        let programs = radiko.find_all_programs(&search_condition).await?;
+
+        assert_eq!(programs.data.len(), 8);
+        let requests = server.requests_to("/v3/api/program/search");
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].target.contains("page_idx"));
+        assert!(requests[1].target.contains("page_idx=1"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_pages_limit_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let mut search_result: serde_json::Value =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+        search_result["data"] = search_result["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .take(5)
+            .cloned()
+            .collect();
+        // page_idxを無視して常に最初のページを返すサーバー
+        server.route(
+            "/v3/api/program/search",
+            MockResponse::ok(search_result.to_string()),
+        );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let search_condition = SearchCondition {
+            key: vec!["トム・ブラウン".to_string()],
+            row_limit: Some(5),
+            ..Default::default()
+        };
+
+        let programs = radiko.find_all_programs(&search_condition).await?;
+
+        // 8件を5件ずつなので2ページで打ち切る
+        assert_eq!(programs.data.len(), 10);
+        assert_eq!(server.requests_to("/v3/api/program/search").len(), 2);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn find_program_from_condition_test() -> Result<()> {
         let search_condition = SearchCondition {
@@ -98,7 +263,7 @@ mod tests {
             station_id: Some(vec!["LFR".to_string()]),
             ..Default::default()
         };
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let result = radiko.find_program(&search_condition).await?;
 
         println!("{:#?}", result);
@@ -110,7 +275,7 @@ mod tests {
     #[tokio::test]
     async fn find_weekly_programs_from_station_test() -> Result<()> {
         let station_id = "LFR";
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let programs = radiko.weekly_programs_from_station(station_id).await?;
 
         println!("{}_weekly_programs: {:#?}", station_id, programs);
@@ -123,7 +288,7 @@ mod tests {
     #[tokio::test]
     async fn program_duration_methods_test() -> Result<()> {
         let station_id = "LFR";
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let programs = radiko
             .weekly_programs_from_station(station_id)
             .await
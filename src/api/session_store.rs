@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use reqwest_cookie_store::CookieStore;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::{api::auth::AppIdentity, error::Result, models::account::AccountInfo};
+
+/// 起動の度にログインやplayerCommon.jsの取得をしないために、認証結果をJSONファイルに保存する
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+/// セッションファイルの内容
+/// 認証情報を含むのでファイルは所有者のみ読み書きできる権限で作成する
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PersistedSession {
+    /// ログインしたアカウントの識別子(メールアドレスのMD5ハッシュ)。非エリアフリーの場合はNone
+    pub account: Option<String>,
+    /// radiko_sessionを含むcookie
+    pub cookies: CookieStore,
+    /// playerCommon.jsから抽出した認証キー
+    pub auth_key: Option<String>,
+    pub auth_token: Option<String>,
+    pub partial_key: Option<String>,
+    pub area_id: Option<String>,
+    /// auth1/auth2で名乗ったアプリの情報
+    #[serde(default)]
+    pub identity: Option<AppIdentity>,
+    pub issued_at: Option<DateTime<Utc>>,
+    /// ログイン時に取得したアカウント情報
+    #[serde(default)]
+    pub account_info: Option<AccountInfo>,
+}
+
+impl SessionStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 保存済みのセッションを読み込む
+    /// ファイルが存在しない、または壊れている場合は新規ログインにフォールバックさせるためNoneを返す
+    pub fn load(&self) -> Option<PersistedSession> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, session: &PersistedSession) -> Result<()> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+
+        // 書き込み途中で落ちても壊れたファイルが残らないように一時ファイルから置き換える
+        let mut temp_file = NamedTempFile::new_in(dir)?;
+        temp_file.write_all(serde_json::to_string_pretty(session)?.as_bytes())?;
+        temp_file.flush()?;
+        temp_file.persist(&self.path).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_test() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = SessionStore::new(dir.path().join("nested/session.json"));
+        assert!(store.load().is_none());
+
+        let mut cookies = CookieStore::default();
+        cookies.parse(
+            "radiko_session=abc; Max-Age=3600",
+            &"https://radiko.jp".parse()?,
+        )?;
+        store.save(&PersistedSession {
+            account: Some("account".to_string()),
+            cookies,
+            auth_key: Some("auth_key".to_string()),
+            auth_token: Some("token".to_string()),
+            partial_key: Some("partial_key".to_string()),
+            area_id: Some("JP13".to_string()),
+            identity: None,
+            issued_at: Some(Utc::now()),
+            account_info: None,
+        })?;
+
+        let loaded = store.load().expect("saved session");
+        assert_eq!(loaded.auth_token.as_deref(), Some("token"));
+        assert!(
+            loaded
+                .cookies
+                .get("radiko.jp", "/", "radiko_session")
+                .is_some()
+        );
+
+        store.clear()?;
+        assert!(store.load().is_none());
+        store.clear()?;
+        Ok(())
+    }
+
+    #[test]
+    fn broken_file_test() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = SessionStore::new(dir.path().join("session.json"));
+        fs::write(&store.path, "{ broken")?;
+
+        assert!(store.load().is_none());
+        Ok(())
+    }
+}
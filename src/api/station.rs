@@ -2,15 +2,14 @@ use std::sync::Arc;
 
 use crate::{
     dto::{region_xml::RegionXml, station_xml::RadikoStationXml},
+    error::Result,
     models::{
         region::{Region, RegionStations},
         station::Stations,
     },
 };
-use anyhow::Result;
-use reqwest::Client;
 
-use super::endpoint::RadikoEndpoint;
+use super::{endpoint::RadikoEndpoint, http::RadikoHttpClient};
 
 #[derive(Debug, Clone)]
 pub struct RadikoStation {
@@ -19,26 +18,29 @@ pub struct RadikoStation {
 
 #[derive(Debug, Clone)]
 struct RadikoStationRef {
-    client: Client,
+    http: RadikoHttpClient,
+    endpoint: RadikoEndpoint,
 }
 
 impl RadikoStation {
-    pub fn new() -> Self {
+    pub fn new(http: RadikoHttpClient, endpoint: RadikoEndpoint) -> Self {
         Self {
-            inner: Arc::new(RadikoStationRef {
-                client: Client::new(),
-            }),
+            inner: Arc::new(RadikoStationRef { http, endpoint }),
         }
     }
 
     pub async fn stations_from_area_id(&self, area_id: &str) -> Result<Stations> {
         let res = self
             .inner
-            .client
-            .get(RadikoEndpoint::station_list_from_area_id_endpoint(area_id))
-            .send()
-            .await?
-            .text()
+            .http
+            .text(
+                self.inner.http.get(
+                    &self
+                        .inner
+                        .endpoint
+                        .station_list_from_area_id_endpoint(area_id),
+                ),
+            )
             .await?;
 
         let radiko_station: RadikoStationXml = quick_xml::de::from_str(&res)?;
@@ -49,11 +51,12 @@ impl RadikoStation {
     pub async fn stations_all(&self) -> Result<Vec<RegionStations>> {
         let res = self
             .inner
-            .client
-            .get(RadikoEndpoint::station_list_all_endpoint())
-            .send()
-            .await?
-            .text()
+            .http
+            .text(
+                self.inner
+                    .http
+                    .get(&self.inner.endpoint.station_list_all_endpoint()),
+            )
             .await?;
 
         let region: RegionXml = quick_xml::de::from_str(&res)?;
@@ -65,12 +68,15 @@ impl RadikoStation {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::radiko::Radiko;
+    use crate::{
+        radiko::{EndpointConfig, Radiko},
+        test_server::{MockResponse, MockServer, mock_auth_routes},
+    };
 
     #[tokio::test]
     async fn get_stations_test() -> Result<()> {
         let area_id = "JP13";
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let stations = radiko.stations_from_area_id(area_id).await?;
 
         println!("{}_stations: {:#?}", area_id, stations);
@@ -82,7 +88,7 @@ mod tests {
 
     #[tokio::test]
     async fn get_station_list_all_test() -> Result<()> {
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let all_station_list = radiko.stations_all().await?;
 
         for region in all_station_list.iter() {
@@ -96,4 +102,25 @@ mod tests {
         assert!(!all_station_list.is_empty());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn stations_from_mock_server_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server.route(
+            "/v3/station/list/JP13.xml",
+            MockResponse::ok(include_str!("../../examples/radiko/JP13.xml")),
+        );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+
+        let stations = radiko
+            .stations_from_area_id(&radiko.area_id().await)
+            .await?;
+
+        assert_eq!(stations.area_id, "JP13");
+        assert_eq!(stations.data[0].id, "TBS");
+        Ok(())
+    }
 }
@@ -1,77 +1,312 @@
-use std::{borrow::Cow, convert::TryFrom, io::Write, sync::Arc};
-
-use anyhow::{Result, anyhow};
-use hls_m3u8::MasterPlaylist;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io::Write,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::{Asia::Tokyo, Tz};
+use futures_util::Stream;
+use reqwest::StatusCode;
 use tempfile::NamedTempFile;
 
-use super::{auth::RadikoAuthManager, endpoint::RadikoEndpoint};
-
+use crate::{
+    dto::stream_xml::RadikoStreamUrlsXml,
+    error::{RadikoError, Result},
+    models::{program::Program, stream::StreamUrls},
+};
+
+use super::{
+    auth::RadikoAuthManager,
+    endpoint::{RadikoEndpoint, TIMEFREE_CHUNK_SECS},
+    hls::{self, HlsLiveSession, HlsSegment, MediaPlaylistContent, resolve_url},
+    http::{RadikoHttpClient, RetryPolicy},
+    live_audio::{self, LiveAudio},
+};
+
+/// タイムフリーで聴取できる期間
+const TIMEFREE_AVAILABLE_DAYS: i64 = 7;
+const TIMEFREE_DATETIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
+#[derive(Clone)]
 pub struct RadikoStream {
     inner: Arc<RadikoStreamRef>,
 }
 
 struct RadikoStreamRef {
     auth_manager: Arc<RadikoAuthManager>,
+    http: RadikoHttpClient,
+    endpoint: RadikoEndpoint,
+    /// ライブ配信のセグメントの再試行方針
+    segment_retry: RetryPolicy,
+    /// ライブ配信のプレイリストの取得に失敗した場合の再接続の方針
+    reconnect: RetryPolicy,
+    /// 放送局ごとのストリームXML
+    stream_urls: RwLock<HashMap<String, StreamUrls>>,
 }
 
 impl RadikoStream {
-    pub fn new(radiko_auth_manager: Arc<RadikoAuthManager>) -> Self {
+    pub fn new(
+        radiko_auth_manager: Arc<RadikoAuthManager>,
+        http: RadikoHttpClient,
+        endpoint: RadikoEndpoint,
+        segment_retry: RetryPolicy,
+        reconnect: RetryPolicy,
+    ) -> Self {
         Self {
             inner: Arc::new(RadikoStreamRef {
                 auth_manager: radiko_auth_manager.clone(),
+                http,
+                endpoint,
+                segment_retry,
+                reconnect,
+                stream_urls: RwLock::new(HashMap::new()),
             }),
         }
     }
 
-    pub fn stream_url(&self, station_id: &str) -> String {
-        let lsid = &self.inner.auth_manager.lsid().to_string();
-        if self.inner.auth_manager.area_free() {
-            RadikoEndpoint::area_free_playlist_create_url_endpoint(station_id, &lsid)
-        } else {
-            RadikoEndpoint::playlist_create_url_endpoint(station_id, &lsid)
+    /// 放送局のストリームXMLに記載されたplaylist_create_urlの一覧
+    /// 取得に成功した結果は放送局ごとにキャッシュする
+    pub async fn stream_urls(&self, station_id: &str) -> Result<StreamUrls> {
+        if let Some(stream_urls) = self
+            .inner
+            .stream_urls
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(station_id)
+        {
+            return Ok(stream_urls.clone());
         }
+
+        let res = self
+            .inner
+            .http
+            .text(
+                self.inner
+                    .http
+                    .get(&self.inner.endpoint.stream_url_list_endpoint(station_id)),
+            )
+            .await?;
+        let stream_urls_xml: RadikoStreamUrlsXml = quick_xml::de::from_str(&res)?;
+        let stream_urls = StreamUrls::from(stream_urls_xml);
+
+        self.inner
+            .stream_urls
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(station_id.to_string(), stream_urls.clone());
+
+        Ok(stream_urls)
+    }
+
+    /// 現在の認証状態で使うplaylist_create_url
+    /// 放送局のストリームXMLが存在しない(404)場合はNone
+    /// 通信やXMLの解析に失敗した場合や、XMLに該当するURLが無い場合はエラーを返す
+    async fn playlist_create_url(
+        &self,
+        station_id: &str,
+        timefree: bool,
+    ) -> Result<Option<String>> {
+        let area_free = self.inner.auth_manager.area_free();
+        let stream_urls = match self.stream_urls(station_id).await {
+            Ok(stream_urls) => stream_urls,
+            Err(RadikoError::HttpStatus { status, .. }) if status == StatusCode::NOT_FOUND => {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        stream_urls
+            .find(area_free, timefree)
+            .map(|stream_url| Some(stream_url.playlist_create_url.clone()))
+            .ok_or_else(|| RadikoError::StreamUrlNotFound {
+                station_id: station_id.to_string(),
+                area_free,
+                timefree,
+            })
+    }
+
+    /// ライブ配信のMasterPlaylist.m3u8のURL
+    /// 放送局がストリームXMLに無い場合は既定のエンドポイントを使う
+    pub async fn stream_url(&self, station_id: &str) -> Result<String> {
+        let lsid = &self.inner.auth_manager.lsid();
+        let area_free = self.inner.auth_manager.area_free();
+        Ok(match self.playlist_create_url(station_id, false).await? {
+            Some(playlist_create_url) => {
+                RadikoEndpoint::live_playlist_url(&playlist_create_url, station_id, lsid, area_free)
+            }
+            None if area_free => self
+                .inner
+                .endpoint
+                .area_free_playlist_create_url_endpoint(station_id, lsid),
+            None => self
+                .inner
+                .endpoint
+                .playlist_create_url_endpoint(station_id, lsid),
+        })
     }
 
     #[allow(dead_code)]
-    pub async fn get_hls_master_playlist_content(&self, station_id: &str) -> Result<Cow<str>> {
-        let master_playlist_res = self
+    pub async fn get_hls_master_playlist_content(&self, station_id: &str) -> Result<Cow<'_, str>> {
+        Ok(self
             .inner
             .auth_manager
-            .http_client()
-            .get(&self.stream_url(station_id))
-            .send()
+            .send_authed(&self.stream_url(station_id).await?)
+            .await?
+            .text()
+            .await?
+            .into())
+    }
+
+    pub fn extract_medialist_url(&self, master_playlist_content: &str) -> Result<Cow<'_, str>> {
+        Ok(hls::extract_medialist_url(master_playlist_content)?.into())
+    }
+
+    /// ライブ配信のメディアプレイリストをポーリングするセッションを開始する
+    pub async fn live_session(&self, station_id: &str) -> Result<HlsLiveSession> {
+        let stream_url = self.stream_url(station_id).await?;
+        let master_playlist_content = self
+            .inner
+            .auth_manager
+            .send_authed(&stream_url)
+            .await?
+            .text()
             .await?;
+        let medialist_url = resolve_url(
+            &stream_url,
+            &self.extract_medialist_url(&master_playlist_content)?,
+        )?;
+
+        Ok(
+            HlsLiveSession::new(self.inner.auth_manager.clone(), medialist_url)
+                .with_master_playlist_url(stream_url)
+                .with_segment_retry(self.inner.segment_retry.clone())
+                .with_reconnect(self.inner.reconnect.clone()),
+        )
+    }
 
-        if !master_playlist_res.status().is_success() {
-            return Err(anyhow!(
-                "get hls master playlist error: {:#?}, client_info: {:#?}",
-                master_playlist_res.text().await?,
-                self.inner.auth_manager.http_client()
-            ));
-        }
+    /// タイムフリーのMasterPlaylist.m3u8のURL
+    /// 番組の先頭から`TIMEFREE_CHUNK_SECS`秒分のプレイリストになるため、番組全体の取得には`timefree_segments`を使う
+    pub async fn timefree_stream_url(&self, program: &Program) -> Result<String> {
+        validate_timefree(program, Utc::now())?;
+        let playlist_create_url = self.timefree_playlist_create_url(program).await?;
+        Ok(self.timefree_chunk_url(playlist_create_url.as_deref(), program, program.start_time))
+    }
 
-        Ok(master_playlist_res.text().await?.into())
+    /// タイムフリーのplaylist_create_url
+    /// 放送局がストリームXMLに無い場合はNoneになり、既定のエンドポイントを使う
+    async fn timefree_playlist_create_url(&self, program: &Program) -> Result<Option<String>> {
+        self.playlist_create_url(&program.station_id, true).await
     }
 
-    #[allow(dead_code)]
-    pub fn extract_medialist_url(&self, master_playlist_content: &str) -> Result<Cow<str>> {
-        let master_playlist = match MasterPlaylist::try_from(master_playlist_content) {
-            Ok(master_playlist) => master_playlist,
-            Err(err) => panic!(
-                "extract_medialist_url error: {:#?},master_playlist_content: {:#?}",
-                err, master_playlist_content
+    fn timefree_chunk_url(
+        &self,
+        playlist_create_url: Option<&str>,
+        program: &Program,
+        seek: DateTime<Tz>,
+    ) -> String {
+        let ft = program
+            .start_time
+            .format(TIMEFREE_DATETIME_FORMAT)
+            .to_string();
+        let to = program
+            .end_time
+            .format(TIMEFREE_DATETIME_FORMAT)
+            .to_string();
+        let seek = seek.format(TIMEFREE_DATETIME_FORMAT).to_string();
+        let lsid = self.inner.auth_manager.lsid();
+        let area_free = self.inner.auth_manager.area_free();
+        match playlist_create_url {
+            Some(playlist_create_url) => RadikoEndpoint::timefree_playlist_url(
+                playlist_create_url,
+                &program.station_id,
+                &ft,
+                &to,
+                &seek,
+                &lsid,
+                area_free,
             ),
-        };
-        Ok(master_playlist
-            .variant_streams
-            .iter()
-            .next()
-            .ok_or_else(|| anyhow!("No stream found in master playlist"))
-            .and_then(|stream| match stream {
-                hls_m3u8::tags::VariantStream::ExtXStreamInf { uri, .. } => Ok(uri.to_string()),
-                _ => Err(anyhow!("Invalid stream type")),
-            })?
-            .into())
+            None => self.inner.endpoint.timefree_playlist_create_url_endpoint(
+                &program.station_id,
+                &ft,
+                &to,
+                &seek,
+                &lsid,
+                area_free,
+            ),
+        }
+    }
+
+    /// タイムフリーの番組全体のセグメント
+    /// seekを進めながら分割されたプレイリストを順に取得し、重複するセグメントを除いて連結する
+    pub async fn timefree_segments(&self, program: &Program) -> Result<Vec<HlsSegment>> {
+        validate_timefree(program, Utc::now())?;
+
+        let mut seen_urls = HashSet::new();
+        let mut segments = Vec::new();
+        let playlist_create_url = self.timefree_playlist_create_url(program).await?;
+        let mut seek = program.start_time;
+        while seek < program.end_time {
+            let master_playlist_url =
+                self.timefree_chunk_url(playlist_create_url.as_deref(), program, seek);
+            let master_playlist_content = self
+                .inner
+                .auth_manager
+                .send_authed(&master_playlist_url)
+                .await?
+                .text()
+                .await?;
+            let medialist_url = resolve_url(
+                &master_playlist_url,
+                &self.extract_medialist_url(&master_playlist_content)?,
+            )?;
+            let media_playlist_content = self
+                .inner
+                .auth_manager
+                .send_authed(&medialist_url)
+                .await?
+                .text()
+                .await?;
+            let media_playlist =
+                MediaPlaylistContent::parse(&media_playlist_content, &medialist_url)?;
+            segments.extend(
+                media_playlist
+                    .segments
+                    .into_iter()
+                    .filter(|segment| seen_urls.insert(segment.url.clone())),
+            );
+
+            seek += chrono::Duration::seconds(TIMEFREE_CHUNK_SECS);
+        }
+
+        Ok(segments)
+    }
+
+    /// ライブ配信のAACを到着順に返すストリーム
+    pub fn live_audio(
+        &self,
+        station_id: &str,
+    ) -> impl Stream<Item = Result<LiveAudio>> + Send + use<> {
+        live_audio::live_audio(self.clone(), station_id)
+    }
+
+    /// 認証ヘッダー付きでGETする
+    /// 401/403の場合は再認証してから1度だけ再試行する
+    #[cfg_attr(not(feature = "relay"), allow(dead_code))]
+    pub async fn send_authed(&self, url: &str) -> Result<reqwest::Response> {
+        self.inner.auth_manager.send_authed(url).await
+    }
+
+    /// セグメントを認証ヘッダー付きでダウンロードする
+    pub async fn download_segment(&self, segment: &HlsSegment) -> Result<Vec<u8>> {
+        Ok(self
+            .inner
+            .auth_manager
+            .send_authed(&segment.url)
+            .await?
+            .bytes()
+            .await?
+            .to_vec())
     }
 
     #[allow(dead_code)]
@@ -79,9 +314,7 @@ impl RadikoStream {
         let playlist_content = self
             .inner
             .auth_manager
-            .http_client()
-            .get(&self.stream_url(station_id))
-            .send()
+            .send_authed(&self.stream_url(station_id).await?)
             .await?
             .bytes()
             .await?;
@@ -94,12 +327,38 @@ impl RadikoStream {
     }
 }
 
+/// 放送が終了していて、放送開始から7日以内の番組であることを確認する
+fn validate_timefree(program: &Program, now: DateTime<Utc>) -> Result<()> {
+    let now = now.with_timezone(&Tokyo);
+    if program.end_time > now {
+        return Err(RadikoError::TimefreeUnavailable(format!(
+            "program has not ended yet: {} ends at {}",
+            program.title, program.end_time
+        )));
+    }
+    if program.start_time < now - chrono::Duration::days(TIMEFREE_AVAILABLE_DAYS) {
+        return Err(RadikoError::TimefreeUnavailable(format!(
+            "program is older than {} days: {} started at {}",
+            TIMEFREE_AVAILABLE_DAYS, program.title, program.start_time
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::api::auth::RadikoAuthManager;
+    use crate::api::{
+        auth::{AuthOptions, AuthStrategy, MobileAuth, RadikoAuthManager},
+        endpoint::RadikoEndpoint,
+        http::RadikoHttpClient,
+    };
+    use crate::error::RadikoError;
+    use crate::models::area::Area;
+    use crate::test_server::{MockResponse, MockServer, mock_auth_routes};
     use crate::utils::load_env;
+    use crate::{api::endpoint::EndpointConfig, radiko::RetryPolicy};
     use crate::{api::stream::RadikoStream, radiko::Radiko};
-    use std::{env, process::Stdio};
+    use std::{env, process::Stdio, sync::Arc};
 
     use anyhow::Result;
 
@@ -111,7 +370,16 @@ mod tests {
     #[tokio::test]
     async fn hls_m3u8_playground() -> Result<()> {
         let station_id = "TBS";
-        let radiko_stream = RadikoStream::new(RadikoAuthManager::new().await.into());
+        let endpoint = RadikoEndpoint::default();
+        let radiko_stream = RadikoStream::new(
+            RadikoAuthManager::new(RadikoHttpClient::default(), endpoint.clone())
+                .await?
+                .into(),
+            RadikoHttpClient::default(),
+            endpoint,
+            RetryPolicy::none(),
+            RetryPolicy::none(),
+        );
 
         let master_playlist_content = radiko_stream
             .get_hls_master_playlist_content(station_id)
@@ -123,13 +391,200 @@ mod tests {
         Ok(())
     }
 
+    const MASTER_PLAYLIST: &str = "#EXTM3U
+#EXT-X-VERSION:6
+#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"
+https://si-f-radiko.smartstream.ne.jp/medialist?session=mock_session
+";
+
+    #[tokio::test]
+    async fn reauthenticate_on_forbidden_test() -> Result<()> {
+        let server = MockServer::start().await;
+        server.route(
+            "/v2/api/auth1",
+            MockResponse::ok("")
+                .header("X-Radiko-Authtoken", "expired_token")
+                .header("X-Radiko-KeyOffset", "0")
+                .header("X-Radiko-KeyLength", "16"),
+        );
+        mock_auth_routes(&server);
+        server
+            .route("/so/playlist.m3u8", MockResponse::status(403))
+            .route("/so/playlist.m3u8", MockResponse::ok(MASTER_PLAYLIST));
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let auth_manager = Arc::new(
+            RadikoAuthManager::new(
+                RadikoHttpClient::new(reqwest::Client::new(), RetryPolicy::none()),
+                endpoint.clone(),
+            )
+            .await?,
+        );
+        assert_eq!(auth_manager.auth_token(), "expired_token");
+        assert!(!auth_manager.is_probably_expired());
+
+        let radiko_stream = RadikoStream::new(
+            auth_manager.clone(),
+            RadikoHttpClient::default(),
+            endpoint,
+            RetryPolicy::none(),
+            RetryPolicy::none(),
+        );
+        let master_playlist_content = radiko_stream.get_hls_master_playlist_content("TBS").await?;
+
+        assert_eq!(master_playlist_content, MASTER_PLAYLIST);
+        assert_eq!(auth_manager.auth_token(), "mock_token");
+        let playlist_requests = server.requests_to("/so/playlist.m3u8");
+        assert_eq!(
+            playlist_requests[0].header("X-Radiko-Authtoken"),
+            Some("expired_token")
+        );
+        assert_eq!(
+            playlist_requests[1].header("X-Radiko-Authtoken"),
+            Some("mock_token")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mobile_auth_playlist_headers_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server.route("/so/playlist.m3u8", MockResponse::ok(MASTER_PLAYLIST));
+        let endpoint = RadikoEndpoint::new(EndpointConfig::single_host(server.base_url()));
+        let auth_manager = RadikoAuthManager::with_options(
+            RadikoHttpClient::default(),
+            endpoint.clone(),
+            AuthOptions {
+                strategy: AuthStrategy::Mobile(MobileAuth::new(
+                    Area::Tokyo,
+                    (0..=255).collect::<Vec<u8>>(),
+                )),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let radiko_stream = RadikoStream::new(
+            auth_manager.into(),
+            RadikoHttpClient::default(),
+            endpoint,
+            RetryPolicy::none(),
+            RetryPolicy::none(),
+        );
+        radiko_stream.get_hls_master_playlist_content("TBS").await?;
+
+        // auth1で名乗ったアプリの情報を認証済みリクエストでも送る
+        let auth1_request = &server.requests_to("/v2/api/auth1")[0];
+        let playlist_request = &server.requests_to("/so/playlist.m3u8")[0];
+        for name in [
+            "X-Radiko-App",
+            "X-Radiko-App-Version",
+            "X-Radiko-User",
+            "X-Radiko-Device",
+        ] {
+            assert!(playlist_request.header(name).is_some());
+            assert_eq!(playlist_request.header(name), auth1_request.header(name));
+        }
+        assert_ne!(playlist_request.header("X-Radiko-Device"), Some("pc"));
+        assert_eq!(
+            playlist_request.header("X-Radiko-Authtoken"),
+            Some("mock_token")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_url_from_stream_xml_test() -> Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server.route(
+            "/v3/station/stream/pc_html5/TBS.xml",
+            MockResponse::ok(format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<urls>
+  <url timefree="0" areafree="1" max_delay="15">
+    <playlist_create_url>{base}/areafree/playlist.m3u8</playlist_create_url>
+  </url>
+  <url timefree="0" areafree="0" max_delay="15">
+    <playlist_create_url>{base}/live/playlist.m3u8</playlist_create_url>
+  </url>
+  <url timefree="1" areafree="0" max_delay="0">
+    <playlist_create_url>{base}/timefree/playlist.m3u8</playlist_create_url>
+  </url>
+</urls>
+"#,
+                base = server.base_url()
+            )),
+        );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+
+        let stream_url = radiko.stream_url("TBS").await?;
+        assert!(stream_url.starts_with(&format!(
+            "{}/live/playlist.m3u8?station_id=TBS&l=15&lsid=",
+            server.base_url()
+        )));
+        assert!(stream_url.ends_with("&type=b"));
+        radiko.stream_url("TBS").await?;
+        assert_eq!(
+            server
+                .requests_to("/v3/station/stream/pc_html5/TBS.xml")
+                .len(),
+            1
+        );
+
+        // ストリームXMLが無い放送局は既定のエンドポイントを使う
+        assert!(radiko.stream_url("QRR").await?.starts_with(&format!(
+            "{}/so/playlist.m3u8?station_id=QRR",
+            server.base_url()
+        )));
+
+        // ストリームXMLが壊れている場合は推測したURLにせずエラーにする
+        server.route(
+            "/v3/station/stream/pc_html5/LFR.xml",
+            MockResponse::ok("<urls><url>"),
+        );
+        assert!(radiko.stream_url("LFR").await.is_err());
+
+        // 現在の認証状態で使えるURLが無い場合もエラーにする
+        server.route(
+            "/v3/station/stream/pc_html5/INT.xml",
+            MockResponse::ok(
+                r#"<urls><url timefree="0" areafree="1" max_delay="15"><playlist_create_url>https://example.com/playlist.m3u8</playlist_create_url></url></urls>"#,
+            ),
+        );
+        assert!(matches!(
+            radiko.stream_url("INT").await,
+            Err(RadikoError::StreamUrlNotFound {
+                area_free: false,
+                timefree: false,
+                ..
+            })
+        ));
+
+        // 既にクエリパラメータを含むURLにはパラメータを付け足す
+        server.route(
+            "/v3/station/stream/pc_html5/FMT.xml",
+            MockResponse::ok(
+                r#"<urls><url timefree="0" areafree="0" max_delay="15"><playlist_create_url>https://example.com/playlist.m3u8?token=abc</playlist_create_url></url></urls>"#,
+            ),
+        );
+        assert!(
+            radiko.stream_url("FMT").await?.starts_with(
+                "https://example.com/playlist.m3u8?token=abc&station_id=FMT&l=15&lsid="
+            )
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn stream_url_test() -> Result<()> {
-        let radiko = Radiko::new().await;
+        let radiko = Radiko::new().await?;
         let available_stations = radiko
             .stations_from_area_id(&radiko.area_id().await)
             .await?;
-        let station_id = available_stations.data.get(0).unwrap().id.clone();
+        let station_id = available_stations.data.first().unwrap().id.clone();
 
         run_ffmpeg_command_stream(radiko, &station_id).await?;
 
@@ -142,7 +597,7 @@ mod tests {
         let mail = env::var("mail").expect("failed mail from dotenv");
         let pass = env::var("pass").expect("failed pass from dotenv");
         let station_id = "MBS";
-        let radiko = Radiko::new_area_free(&mail, &pass).await;
+        let radiko = Radiko::new_area_free(&mail, &pass).await?;
 
         println!("station_id: {}", station_id);
 
@@ -152,7 +607,7 @@ mod tests {
     }
 
     async fn run_ffmpeg_command_stream(radiko: Radiko, station_id: &str) -> Result<()> {
-        let strem_url = radiko.stream_url(station_id).await;
+        let strem_url = radiko.stream_url(station_id).await?;
         let token = radiko.auth_token().await.to_string();
 
         let cmd = Command::new("ffmpeg")
@@ -214,7 +669,7 @@ mod tests {
                 if let Some(stderr) = child.stderr.take() {
                     let reader = BufReader::new(stderr);
                     let mut lines = reader.lines();
-                    let log_stream_id = stream_id.clone();
+                    let log_stream_id = stream_id;
 
                     tokio::spawn(async move {
                         while let Ok(Some(line)) = lines.next_line().await {
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+// ex: https://radiko.jp/v4/api/member/login
+// 値は全て文字列で返却される("1" / "0")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginResponse {
+    pub twitter_name: Option<String>,
+    pub status: String,
+    pub unpaid: String,
+    pub radiko_session: String,
+    pub areafree: String,
+    pub member_ukey: String,
+    pub facebook_name: Option<String>,
+    pub privileges: Vec<String>,
+    pub paid_member: String,
+}
@@ -1,4 +1,7 @@
+pub mod login_json;
 pub mod logo_xml;
 pub mod program_xml;
 pub mod region_xml;
+pub mod search_json;
 pub mod station_xml;
+pub mod stream_xml;
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+// ex: https://radiko.jp/v3/api/program/search
+// 内容はexamples/radiko/search_result.jsonを参照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResultJson {
+    #[serde(default)]
+    pub meta: SearchMetaJson,
+    pub data: Vec<ProgramJson>,
+}
+
+/// 検索条件と件数。指定されていない条件は空文字列や空配列になる
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchMetaJson {
+    pub key: Vec<String>,
+    pub station_id: Vec<String>,
+    pub area_id: Vec<String>,
+    pub cur_area_id: String,
+    pub region_id: String,
+    pub start_day: String,
+    pub end_day: String,
+    pub filter: String,
+    pub result_count: u32,
+    pub page_idx: u32,
+    pub row_limit: u32,
+    pub kakuchou: Vec<String>,
+    pub suisengo: String,
+    pub genre_id: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgramJson {
+    /// 番組表の番組IDは含まれないことがある
+    pub id: Option<String>,
+    /// `2025-06-29 00:00:00`形式
+    pub start_time: String,
+    pub end_time: String,
+    pub start_time_s: String,
+    pub end_time_s: String,
+    /// `20250628`形式
+    pub program_date: Option<String>,
+    pub program_url: Option<String>,
+    pub station_id: String,
+    pub performer: Option<String>,
+    pub title: String,
+    pub info: Option<String>,
+    pub description: Option<String>,
+    pub img: Option<String>,
+    #[serde(default)]
+    pub genre: GenreJson,
+    pub ts_in_ng: Option<u8>,
+    pub ts_out_ng: Option<u8>,
+    pub tsplus_in_ng: Option<u8>,
+    pub tsplus_out_ng: Option<u8>,
+    #[serde(default)]
+    pub metas: Vec<MetaJson>,
+}
+
+/// ジャンルが無い場合は`{}`が返却される
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenreJson {
+    pub program: Option<GenreItemJson>,
+    pub personality: Option<GenreItemJson>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreItemJson {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaJson {
+    pub name: String,
+    pub value: String,
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+// ex: https://radiko.jp/v3/station/stream/pc_html5/TBS.xml
+// ```xml
+// <urls>
+//   <url timefree="0" areafree="0" max_delay="15">
+//     <playlist_create_url>https://si-f-radiko.smartstream.ne.jp/so/playlist.m3u8</playlist_create_url>
+//   </url>
+//   <url timefree="1" areafree="0" max_delay="0">
+//     <playlist_create_url>https://tf-f-rpaa-radiko.smartstream.ne.jp/tf/playlist.m3u8</playlist_create_url>
+//   </url>
+// </urls>
+// ```
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename = "urls")]
+pub struct RadikoStreamUrlsXml {
+    #[serde(rename = "url", default)]
+    pub urls: Vec<StreamUrlXml>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StreamUrlXml {
+    #[serde(rename = "@timefree")]
+    pub timefree: u8,
+    #[serde(rename = "@areafree")]
+    pub areafree: u8,
+    #[serde(rename = "@max_delay")]
+    pub max_delay: u32,
+    pub playlist_create_url: String,
+}
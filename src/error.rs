@@ -0,0 +1,96 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, RadikoError>;
+
+/// radiko-rsの公開APIが返すエラー
+/// radiko側の仕様変更で発生し得る失敗は個別のバリアントとして返すので、呼び出し側でmatchして対処できる
+#[derive(Debug, Error)]
+pub enum RadikoError {
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("unexpected http status {status} from {url}: {body}")]
+    HttpStatus {
+        status: StatusCode,
+        url: String,
+        body: String,
+    },
+
+    /// playerCommon.jsから認証キーを抽出できなかった
+    #[error("auth key not found in player script")]
+    AuthKeyNotFound,
+
+    /// radiko.jp/area/ のレスポンスからarea_idを抽出できなかった
+    #[error("failed to detect area_id: {0}")]
+    AreaDetectionFailed(String),
+
+    #[error("login failed: {0}")]
+    LoginFailed(String),
+
+    /// 国外からのアクセスなどでradikoのエリア判定外になった
+    #[error("out of area: {0}")]
+    OutOfArea(String),
+
+    /// auth1/auth2が失敗した
+    #[error("auth failed: {0}")]
+    AuthFailed(String),
+
+    #[error("missing response header: {0}")]
+    MissingHeader(&'static str),
+
+    #[error("invalid header: {0}")]
+    InvalidHeader(String),
+
+    #[error("xml parse error: {0}")]
+    XmlParse(#[from] quick_xml::DeError),
+
+    #[error("json parse error: {0}")]
+    JsonParse(#[from] serde_json::Error),
+
+    #[error("playlist parse error: {0}")]
+    PlaylistParse(String),
+
+    /// 録音したAACをコンテナに格納できなかった
+    #[error("invalid audio data: {0}")]
+    InvalidAudio(String),
+
+    #[error("datetime parse error: {0}")]
+    DateTimeParse(String),
+
+    /// 放送局のストリームXMLに、現在の認証状態で使える配信URLが無い
+    #[error("stream url not found for {station_id} (area_free: {area_free}, timefree: {timefree})")]
+    StreamUrlNotFound {
+        station_id: String,
+        area_free: bool,
+        timefree: bool,
+    },
+
+    /// 放送前や放送から7日以上経過した番組をタイムフリーで取得しようとした
+    #[error("timefree unavailable: {0}")]
+    TimefreeUnavailable(String),
+
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<reqwest::header::InvalidHeaderValue> for RadikoError {
+    fn from(value: reqwest::header::InvalidHeaderValue) -> Self {
+        Self::InvalidHeader(value.to_string())
+    }
+}
+
+impl From<reqwest::header::ToStrError> for RadikoError {
+    fn from(value: reqwest::header::ToStrError) -> Self {
+        Self::InvalidHeader(value.to_string())
+    }
+}
+
+impl From<chrono::ParseError> for RadikoError {
+    fn from(value: chrono::ParseError) -> Self {
+        Self::DateTimeParse(value.to_string())
+    }
+}
@@ -1,5 +1,11 @@
 pub(crate) mod api;
 mod dto;
+pub mod error;
 pub mod models;
 pub mod radiko;
+pub mod recorder;
+#[cfg(feature = "relay")]
+pub mod relay;
+#[cfg(test)]
+mod test_server;
 mod utils;
@@ -0,0 +1,71 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::dto::login_json::LoginResponse;
+
+/// エリアフリーでログインしたアカウントの情報
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    /// プレミアム会員(有料会員)であればtrue
+    pub premium: bool,
+    /// エリアフリーで聴取できる場合にtrue
+    pub area_free: bool,
+    /// 会費の未払いがある場合にtrue
+    pub unpaid: bool,
+    pub privileges: Vec<String>,
+    pub member_ukey: String,
+    pub twitter_name: Option<String>,
+    pub facebook_name: Option<String>,
+}
+
+impl AccountInfo {
+    /// 未払いがなく、エリアフリーで聴取できるプレミアム会員であればtrue
+    pub fn can_listen_area_free(&self) -> bool {
+        self.premium && self.area_free && !self.unpaid
+    }
+
+    pub fn has_privilege(&self, privilege: &str) -> bool {
+        self.privileges.iter().any(|p| p == privilege)
+    }
+}
+
+impl From<LoginResponse> for AccountInfo {
+    fn from(value: LoginResponse) -> Self {
+        AccountInfo {
+            premium: value.paid_member == "1",
+            area_free: value.areafree == "1",
+            unpaid: value.unpaid == "1",
+            privileges: value.privileges,
+            member_ukey: value.member_ukey,
+            twitter_name: value.twitter_name,
+            facebook_name: value.facebook_name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_login_response_test() -> anyhow::Result<()> {
+        let login_response: LoginResponse = serde_json::from_str(
+            r#"{"twitter_name":null,"status":"200","unpaid":"0","radiko_session":"session","areafree":"1","member_ukey":"ukey","facebook_name":"fb","privileges":["areafree","timefree30"],"paid_member":"1"}"#,
+        )?;
+        let account_info = AccountInfo::from(login_response);
+
+        assert!(account_info.premium);
+        assert!(account_info.area_free);
+        assert!(!account_info.unpaid);
+        assert!(account_info.can_listen_area_free());
+        assert!(account_info.has_privilege("timefree30"));
+        assert_eq!(account_info.facebook_name.as_deref(), Some("fb"));
+
+        let free_account = AccountInfo {
+            premium: false,
+            area_free: false,
+            ..account_info
+        };
+        assert!(!free_account.can_listen_area_free());
+        Ok(())
+    }
+}
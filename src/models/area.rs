@@ -0,0 +1,360 @@
+use std::{fmt, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::error::RadikoError;
+
+/// radikoのエリア(都道府県)
+/// `"JP13"`のようなarea_idとして文字列で表示・シリアライズする
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, SerializeDisplay, DeserializeFromStr,
+)]
+pub enum Area {
+    Hokkaido = 1,
+    Aomori,
+    Iwate,
+    Miyagi,
+    Akita,
+    Yamagata,
+    Fukushima,
+    Ibaraki,
+    Tochigi,
+    Gunma,
+    Saitama,
+    Chiba,
+    Tokyo,
+    Kanagawa,
+    Niigata,
+    Toyama,
+    Ishikawa,
+    Fukui,
+    Yamanashi,
+    Nagano,
+    Gifu,
+    Shizuoka,
+    Aichi,
+    Mie,
+    Shiga,
+    Kyoto,
+    Osaka,
+    Hyogo,
+    Nara,
+    Wakayama,
+    Tottori,
+    Shimane,
+    Okayama,
+    Hiroshima,
+    Yamaguchi,
+    Tokushima,
+    Kagawa,
+    Ehime,
+    Kochi,
+    Fukuoka,
+    Saga,
+    Nagasaki,
+    Kumamoto,
+    Oita,
+    Miyazaki,
+    Kagoshima,
+    Okinawa,
+}
+
+/// 放送局一覧の地域
+/// `RegionStations::region_id`に対応する。全国放送の`zenkoku`は含まない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AreaRegion {
+    HokkaidoTohoku,
+    Kanto,
+    HokurikuKoushinetsu,
+    Chubu,
+    Kinki,
+    ChugokuShikoku,
+    Kyushu,
+}
+
+/// (エリア, area_id, 名前, 都道府県名, ローマ字表記, (緯度, 経度))
+/// 座標は各都道府県庁の所在地
+type AreaRow = (
+    Area,
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static str,
+    (f64, f64),
+);
+
+/// area_idの番号順に並べる
+#[rustfmt::skip]
+const AREAS: [AreaRow; 47] = [
+    (Area::Hokkaido, "JP1", "北海道", "北海道", "Hokkaido", (43.064615, 141.346807)),
+    (Area::Aomori, "JP2", "青森", "青森県", "Aomori", (40.824308, 140.739998)),
+    (Area::Iwate, "JP3", "岩手", "岩手県", "Iwate", (39.703619, 141.152684)),
+    (Area::Miyagi, "JP4", "宮城", "宮城県", "Miyagi", (38.268837, 140.8721)),
+    (Area::Akita, "JP5", "秋田", "秋田県", "Akita", (39.718614, 140.102364)),
+    (Area::Yamagata, "JP6", "山形", "山形県", "Yamagata", (38.240436, 140.363633)),
+    (Area::Fukushima, "JP7", "福島", "福島県", "Fukushima", (37.750299, 140.467551)),
+    (Area::Ibaraki, "JP8", "茨城", "茨城県", "Ibaraki", (36.341811, 140.446793)),
+    (Area::Tochigi, "JP9", "栃木", "栃木県", "Tochigi", (36.565725, 139.883565)),
+    (Area::Gunma, "JP10", "群馬", "群馬県", "Gunma", (36.390668, 139.060406)),
+    (Area::Saitama, "JP11", "埼玉", "埼玉県", "Saitama", (35.856999, 139.648849)),
+    (Area::Chiba, "JP12", "千葉", "千葉県", "Chiba", (35.605057, 140.123306)),
+    (Area::Tokyo, "JP13", "東京", "東京都", "Tokyo", (35.689488, 139.691706)),
+    (Area::Kanagawa, "JP14", "神奈川", "神奈川県", "Kanagawa", (35.447507, 139.642345)),
+    (Area::Niigata, "JP15", "新潟", "新潟県", "Niigata", (37.902552, 139.023095)),
+    (Area::Toyama, "JP16", "富山", "富山県", "Toyama", (36.695291, 137.211338)),
+    (Area::Ishikawa, "JP17", "石川", "石川県", "Ishikawa", (36.594682, 136.625573)),
+    (Area::Fukui, "JP18", "福井", "福井県", "Fukui", (36.065178, 136.221527)),
+    (Area::Yamanashi, "JP19", "山梨", "山梨県", "Yamanashi", (35.664158, 138.568449)),
+    (Area::Nagano, "JP20", "長野", "長野県", "Nagano", (36.651299, 138.180956)),
+    (Area::Gifu, "JP21", "岐阜", "岐阜県", "Gifu", (35.391227, 136.722291)),
+    (Area::Shizuoka, "JP22", "静岡", "静岡県", "Shizuoka", (34.97712, 138.383084)),
+    (Area::Aichi, "JP23", "愛知", "愛知県", "Aichi", (35.180188, 136.906565)),
+    (Area::Mie, "JP24", "三重", "三重県", "Mie", (34.730283, 136.508588)),
+    (Area::Shiga, "JP25", "滋賀", "滋賀県", "Shiga", (35.004531, 135.86859)),
+    (Area::Kyoto, "JP26", "京都", "京都府", "Kyoto", (35.021247, 135.755597)),
+    (Area::Osaka, "JP27", "大阪", "大阪府", "Osaka", (34.686297, 135.519661)),
+    (Area::Hyogo, "JP28", "兵庫", "兵庫県", "Hyogo", (34.691269, 135.183071)),
+    (Area::Nara, "JP29", "奈良", "奈良県", "Nara", (34.685334, 135.832742)),
+    (Area::Wakayama, "JP30", "和歌山", "和歌山県", "Wakayama", (34.225987, 135.167509)),
+    (Area::Tottori, "JP31", "鳥取", "鳥取県", "Tottori", (35.503891, 134.237736)),
+    (Area::Shimane, "JP32", "島根", "島根県", "Shimane", (35.472295, 133.0505)),
+    (Area::Okayama, "JP33", "岡山", "岡山県", "Okayama", (34.661751, 133.934406)),
+    (Area::Hiroshima, "JP34", "広島", "広島県", "Hiroshima", (34.39656, 132.459622)),
+    (Area::Yamaguchi, "JP35", "山口", "山口県", "Yamaguchi", (34.185956, 131.470649)),
+    (Area::Tokushima, "JP36", "徳島", "徳島県", "Tokushima", (34.065718, 134.55936)),
+    (Area::Kagawa, "JP37", "香川", "香川県", "Kagawa", (34.340149, 134.043444)),
+    (Area::Ehime, "JP38", "愛媛", "愛媛県", "Ehime", (33.841624, 132.765681)),
+    (Area::Kochi, "JP39", "高知", "高知県", "Kochi", (33.559706, 133.531079)),
+    (Area::Fukuoka, "JP40", "福岡", "福岡県", "Fukuoka", (33.606576, 130.418297)),
+    (Area::Saga, "JP41", "佐賀", "佐賀県", "Saga", (33.249442, 130.299794)),
+    (Area::Nagasaki, "JP42", "長崎", "長崎県", "Nagasaki", (32.744839, 129.873756)),
+    (Area::Kumamoto, "JP43", "熊本", "熊本県", "Kumamoto", (32.789827, 130.741667)),
+    (Area::Oita, "JP44", "大分", "大分県", "Oita", (33.238172, 131.612619)),
+    (Area::Miyazaki, "JP45", "宮崎", "宮崎県", "Miyazaki", (31.911096, 131.423893)),
+    (Area::Kagoshima, "JP46", "鹿児島", "鹿児島県", "Kagoshima", (31.560146, 130.557978)),
+    (Area::Okinawa, "JP47", "沖縄", "沖縄県", "Okinawa", (26.2124, 127.680932)),
+];
+
+impl Area {
+    /// area_idの番号順の全エリア
+    pub fn all() -> impl Iterator<Item = Area> {
+        AREAS.iter().map(|(area, ..)| *area)
+    }
+
+    /// area_idの番号(1〜47)
+    pub fn number(&self) -> u8 {
+        *self as u8
+    }
+
+    /// `"JP13"`のようなarea_id
+    pub fn id(&self) -> &'static str {
+        AREAS[self.index()].1
+    }
+
+    /// `"東京"`のような都道府県名から「都府県」を除いた名前
+    pub fn name(&self) -> &'static str {
+        AREAS[self.index()].2
+    }
+
+    /// `"東京都"`のような都道府県名
+    pub fn prefecture_name(&self) -> &'static str {
+        AREAS[self.index()].3
+    }
+
+    /// `"Tokyo"`のようなローマ字表記
+    pub fn romaji(&self) -> &'static str {
+        AREAS[self.index()].4
+    }
+
+    /// 都道府県庁の所在地の(緯度, 経度)
+    pub fn location(&self) -> (f64, f64) {
+        AREAS[self.index()].5
+    }
+
+    pub fn region(&self) -> AreaRegion {
+        match self.number() {
+            1..=7 => AreaRegion::HokkaidoTohoku,
+            8..=14 => AreaRegion::Kanto,
+            15..=20 => AreaRegion::HokurikuKoushinetsu,
+            21..=24 => AreaRegion::Chubu,
+            25..=30 => AreaRegion::Kinki,
+            31..=39 => AreaRegion::ChugokuShikoku,
+            _ => AreaRegion::Kyushu,
+        }
+    }
+
+    /// area_idの番号(1〜47)からエリアを取得する
+    pub fn from_number(number: u8) -> Option<Self> {
+        AREAS
+            .get(usize::from(number).checked_sub(1)?)
+            .map(|(area, ..)| *area)
+    }
+
+    fn index(&self) -> usize {
+        usize::from(self.number()) - 1
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl AsRef<str> for Area {
+    fn as_ref(&self) -> &str {
+        self.id()
+    }
+}
+
+impl From<Area> for String {
+    fn from(area: Area) -> Self {
+        area.id().to_string()
+    }
+}
+
+/// `"JP13"`のようなarea_id、`"東京"`、`"東京都"`、`"Tokyo"`(大文字小文字は区別しない)から変換する
+impl FromStr for Area {
+    type Err = RadikoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(digits) = s.strip_prefix("JP")
+            && !digits.starts_with('0')
+            && digits.bytes().all(|b| b.is_ascii_digit())
+            && let Ok(number) = digits.parse()
+            && let Some(area) = Self::from_number(number)
+        {
+            return Ok(area);
+        }
+        AREAS
+            .iter()
+            .find(|(_, _, name, prefecture_name, romaji, _)| {
+                s == *name || s == *prefecture_name || s.eq_ignore_ascii_case(romaji)
+            })
+            .map(|(area, ..)| *area)
+            .ok_or_else(|| RadikoError::InvalidArgument(format!("unknown area: {}", s)))
+    }
+}
+
+impl TryFrom<&str> for Area {
+    type Error = RadikoError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl AreaRegion {
+    pub fn all() -> [AreaRegion; 7] {
+        [
+            Self::HokkaidoTohoku,
+            Self::Kanto,
+            Self::HokurikuKoushinetsu,
+            Self::Chubu,
+            Self::Kinki,
+            Self::ChugokuShikoku,
+            Self::Kyushu,
+        ]
+    }
+
+    /// `RegionStations::region_id`
+    pub fn region_id(&self) -> &'static str {
+        match self {
+            Self::HokkaidoTohoku => "hokkaido-tohoku",
+            Self::Kanto => "kanto",
+            Self::HokurikuKoushinetsu => "hokuriku-koushinetsu",
+            Self::Chubu => "chubu",
+            Self::Kinki => "kinki",
+            Self::ChugokuShikoku => "chugoku-shikoku",
+            Self::Kyushu => "kyushu",
+        }
+    }
+
+    /// `RegionStations::region_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HokkaidoTohoku => "北海道・東北",
+            Self::Kanto => "関東",
+            Self::HokurikuKoushinetsu => "北陸・甲信越",
+            Self::Chubu => "中部",
+            Self::Kinki => "近畿",
+            Self::ChugokuShikoku => "中国・四国",
+            Self::Kyushu => "九州・沖縄",
+        }
+    }
+
+    /// 地域に含まれるエリア
+    pub fn areas(&self) -> impl Iterator<Item = Area> + use<> {
+        let region = *self;
+        Area::all().filter(move |area| area.region() == region)
+    }
+
+    pub fn from_region_id(region_id: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|region| region.region_id() == region_id)
+    }
+}
+
+impl AsRef<str> for AreaRegion {
+    fn as_ref(&self) -> &str {
+        self.region_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn area_catalog_test() {
+        assert_eq!(Area::all().count(), 47);
+        for (index, area) in Area::all().enumerate() {
+            assert_eq!(area.number() as usize, index + 1);
+            assert_eq!(area.id(), format!("JP{}", index + 1));
+            assert_eq!(area.id().parse::<Area>().unwrap(), area);
+        }
+        assert_eq!(Area::Tokyo.id(), "JP13");
+        assert_eq!(Area::Tokyo.name(), "東京");
+        assert_eq!(Area::Tokyo.prefecture_name(), "東京都");
+        assert_eq!(Area::Tokyo.region(), AreaRegion::Kanto);
+        assert_eq!(Area::Tokyo.location(), (35.689488, 139.691706));
+        assert_eq!(Area::Okinawa.location(), (26.2124, 127.680932));
+        assert_eq!(
+            AreaRegion::from_region_id("hokuriku-koushinetsu")
+                .unwrap()
+                .areas()
+                .collect::<Vec<_>>(),
+            vec![
+                Area::Niigata,
+                Area::Toyama,
+                Area::Ishikawa,
+                Area::Fukui,
+                Area::Yamanashi,
+                Area::Nagano
+            ]
+        );
+        assert_eq!(AreaRegion::from_region_id("zenkoku"), None);
+    }
+
+    #[test]
+    fn parse_area_test() {
+        assert_eq!("JP13".parse::<Area>().unwrap(), Area::Tokyo);
+        assert_eq!("東京".parse::<Area>().unwrap(), Area::Tokyo);
+        assert_eq!("東京都".parse::<Area>().unwrap(), Area::Tokyo);
+        assert_eq!("hokkaido".parse::<Area>().unwrap(), Area::Hokkaido);
+        assert_eq!(Area::try_from("JP1").unwrap(), Area::Hokkaido);
+        for invalid in ["JP0", "JP48", "JP013", "jp13", "", "OUT"] {
+            assert!(matches!(
+                invalid.parse::<Area>(),
+                Err(RadikoError::InvalidArgument(_))
+            ));
+        }
+        assert_eq!(serde_json::to_string(&Area::Osaka).unwrap(), r#""JP27""#);
+        assert_eq!(
+            serde_json::from_str::<Area>(r#""JP27""#).unwrap(),
+            Area::Osaka
+        );
+    }
+}
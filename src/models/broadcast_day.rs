@@ -0,0 +1,198 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::{Asia::Tokyo, Tz};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use crate::error::{RadikoError, Result};
+
+/// 放送日が切り替わる時刻
+pub const BROADCAST_DAY_START_HOUR: u32 = 5;
+
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// radikoの番組表の放送日
+/// 5時から翌日の5時までを1日とし、深夜0時〜5時の番組は前日の放送日に含まれる
+/// 番組表では深夜の時刻を`2530`(翌1時30分)のように24時以降の表記で表す
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct BroadcastDay {
+    date: NaiveDate,
+}
+
+impl BroadcastDay {
+    pub fn new(date: NaiveDate) -> Self {
+        Self { date }
+    }
+
+    /// 日時が含まれる放送日
+    pub fn of<T: TimeZone>(datetime: &DateTime<T>) -> Self {
+        let datetime = datetime.with_timezone(&Tokyo);
+        let date = datetime.date_naive();
+        if datetime.hour() < BROADCAST_DAY_START_HOUR {
+            Self::new(date.pred_opt().unwrap_or(date))
+        } else {
+            Self::new(date)
+        }
+    }
+
+    /// 現在の放送日
+    pub fn today() -> Self {
+        Self::of(&Utc::now())
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn next(&self) -> Self {
+        Self::new(self.date + Days::new(1))
+    }
+
+    pub fn previous(&self) -> Self {
+        Self::new(self.date - Days::new(1))
+    }
+
+    /// 放送日の開始日時(5時)
+    pub fn start(&self) -> DateTime<Tz> {
+        self.at_minutes(BROADCAST_DAY_START_HOUR * 60)
+    }
+
+    /// 放送日の終了日時(翌日の5時)
+    pub fn end(&self) -> DateTime<Tz> {
+        self.next().start()
+    }
+
+    pub fn contains<T: TimeZone>(&self, datetime: &DateTime<T>) -> bool {
+        Self::of(datetime) == *self
+    }
+
+    /// `2530`のような24時以降を含む`HHMM`表記を日時にする
+    /// 5時より前の時刻は翌日として扱うため、`0130`も`2530`と同じ日時になる
+    pub fn parse_hhmm(&self, hhmm: &str) -> Result<DateTime<Tz>> {
+        let invalid = || RadikoError::DateTimeParse(format!("invalid HHMM: {}", hhmm));
+        if hhmm.len() != 4 || !hhmm.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let hour: u32 = hhmm[..2].parse().map_err(|_| invalid())?;
+        let minute: u32 = hhmm[2..].parse().map_err(|_| invalid())?;
+        // 放送日の終了日時の`2900`より後は次の放送日になる
+        let end_hour = 24 + BROADCAST_DAY_START_HOUR;
+        if minute >= 60 || hour > end_hour || (hour == end_hour && minute > 0) {
+            return Err(invalid());
+        }
+        let hour = if hour < BROADCAST_DAY_START_HOUR {
+            hour + 24
+        } else {
+            hour
+        };
+        Ok(self.at_minutes(hour * 60 + minute))
+    }
+
+    /// 日時を放送日の`2530`のような`HHMM`表記にする
+    /// 放送日の終了日時は`2900`になる
+    pub fn to_hhmm<T: TimeZone>(&self, datetime: &DateTime<T>) -> String {
+        let (hour, minute) = self.hour_minute(datetime);
+        format!("{:02}{:02}", hour, minute)
+    }
+
+    /// 日時を放送日の`25:30`のような30時間制の表記にする
+    pub fn format_30h<T: TimeZone>(&self, datetime: &DateTime<T>) -> String {
+        let (hour, minute) = self.hour_minute(datetime);
+        format!("{:02}:{:02}", hour, minute)
+    }
+
+    /// 放送日の0時からの経過時間を時と分にする
+    fn hour_minute<T: TimeZone>(&self, datetime: &DateTime<T>) -> (i64, i64) {
+        let minutes = datetime
+            .with_timezone(&Tokyo)
+            .signed_duration_since(self.at_minutes(0))
+            .num_minutes();
+        (minutes.div_euclid(60), minutes.rem_euclid(60))
+    }
+
+    /// 放送日の日付の0時からminutes分後の日時
+    fn at_minutes(&self, minutes: u32) -> DateTime<Tz> {
+        let midnight = self.date.and_time(NaiveTime::MIN);
+        let naive = midnight + chrono::Duration::minutes(minutes.into());
+        // 日本には夏時間が無いので一意に決まる
+        Tokyo
+            .from_local_datetime(&naive)
+            .single()
+            .expect("JST has no ambiguous local time")
+    }
+}
+
+impl From<NaiveDate> for BroadcastDay {
+    fn from(date: NaiveDate) -> Self {
+        Self::new(date)
+    }
+}
+
+impl From<BroadcastDay> for NaiveDate {
+    fn from(day: BroadcastDay) -> Self {
+        day.date
+    }
+}
+
+/// `20250628`形式
+impl fmt::Display for BroadcastDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date.format(DATE_FORMAT))
+    }
+}
+
+impl FromStr for BroadcastDay {
+    type Err = RadikoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self::new(NaiveDate::parse_from_str(s, DATE_FORMAT)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jst(day: u32, hour: u32, minute: u32) -> DateTime<Tz> {
+        Tokyo
+            .with_ymd_and_hms(2025, 6, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn broadcast_day_boundary_test() {
+        let day: BroadcastDay = "20250628".parse().unwrap();
+
+        assert_eq!(BroadcastDay::of(&jst(29, 1, 0)), day);
+        assert_eq!(BroadcastDay::of(&jst(29, 4, 59)), day);
+        assert_eq!(BroadcastDay::of(&jst(29, 5, 0)), day.next());
+        assert_eq!(BroadcastDay::of(&jst(28, 5, 0)), day);
+        assert_eq!(BroadcastDay::of(&jst(28, 19, 0).with_timezone(&Utc)), day);
+        assert_eq!(day.start(), jst(28, 5, 0));
+        assert_eq!(day.end(), jst(29, 5, 0));
+        assert!(day.contains(&jst(29, 0, 0)));
+        assert!(!day.contains(&jst(29, 5, 0)));
+        assert_eq!(day.previous().to_string(), "20250627");
+    }
+
+    #[test]
+    fn extended_hhmm_test() {
+        let day = BroadcastDay::new(NaiveDate::from_ymd_opt(2025, 6, 28).unwrap());
+
+        assert_eq!(day.parse_hhmm("2400").unwrap(), jst(29, 0, 0));
+        assert_eq!(day.parse_hhmm("2530").unwrap(), jst(29, 1, 30));
+        assert_eq!(day.parse_hhmm("0130").unwrap(), jst(29, 1, 30));
+        assert_eq!(day.parse_hhmm("0500").unwrap(), jst(28, 5, 0));
+        assert_eq!(day.parse_hhmm("2900").unwrap(), jst(29, 5, 0));
+        for invalid in ["3000", "2901", "2959", "2560", "530", "25:30", ""] {
+            assert!(day.parse_hhmm(invalid).is_err());
+        }
+
+        assert_eq!(day.to_hhmm(&jst(29, 1, 30)), "2530");
+        assert_eq!(day.to_hhmm(&jst(28, 5, 0)), "0500");
+        assert_eq!(day.to_hhmm(&jst(29, 5, 0)), "2900");
+        assert_eq!(day.format_30h(&jst(29, 1, 30)), "25:30");
+    }
+}
@@ -1,5 +1,10 @@
+pub mod account;
+pub mod area;
+pub mod broadcast_day;
 pub mod logo;
 pub mod program;
 pub mod region;
 pub mod search;
 pub mod station;
+pub mod station_directory;
+pub mod stream;
@@ -1,8 +1,17 @@
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::{collections::BTreeMap, ops::Range, time::Duration};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
 use chrono_tz::{Asia::Tokyo, Tz};
 use serde_derive::{Deserialize, Serialize};
 
-use crate::dto::program_xml::{ProgramXml, RadikoProgramXml};
+use crate::{
+    dto::{
+        program_xml::{GenreXml, ProgramXml, RadikoProgramXml},
+        search_json::{GenreItemJson, GenreJson, ProgramJson, SearchResultJson},
+    },
+    error::{RadikoError, Result},
+    models::broadcast_day::BroadcastDay,
+};
 
 // ```json
 // "data": [
@@ -93,6 +102,98 @@ pub struct Program {
     pub info: String,
     pub description: String,
     pub img: String,
+    /// 番組表の番組ID。検索結果には含まれないことがある
+    #[serde(default)]
+    pub program_id: Option<String>,
+    /// 同じ番組の放送回で共通のID
+    #[serde(default)]
+    pub master_id: Option<String>,
+    /// 放送時間。番組表に記載が無い場合は開始から終了までの時間
+    #[serde(default)]
+    pub duration: Duration,
+    /// 番組のWebサイト
+    #[serde(default)]
+    pub url: Option<String>,
+    /// 番組表に記載された関連リンク
+    #[serde(default)]
+    pub url_link: Option<String>,
+    /// 番組が属する放送日。深夜の番組は前日の日付になる
+    #[serde(default)]
+    pub program_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub genres: ProgramGenres,
+    #[serde(default)]
+    pub metas: Vec<ProgramMeta>,
+    #[serde(default)]
+    pub timefree: TimefreeAvailability,
+    /// 録音に失敗し、タイムフリーで配信されない
+    #[serde(default)]
+    pub failed_record: bool,
+}
+
+/// 番組のジャンルと出演者のジャンル
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramGenres {
+    pub program: Vec<Genre>,
+    pub personality: Vec<Genre>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Genre {
+    /// 番組のジャンルは`P006`、出演者のジャンルは`C010`のような形式
+    pub id: String,
+    pub name: String,
+}
+
+/// `twitter`のハッシュタグなど、番組に付与された追加情報
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramMeta {
+    pub name: String,
+    pub value: String,
+}
+
+/// タイムフリーで聴取できるかどうか
+/// 番組表の`ts_in_ng`、`ts_out_ng`、`tsplus_in_ng`、`tsplus_out_ng`を反転したもの。記載が無い場合は聴取できる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimefreeAvailability {
+    /// エリア内で聴取できる
+    pub in_area: bool,
+    /// エリアフリーでエリア外から聴取できる
+    pub out_of_area: bool,
+    /// タイムフリー30でエリア内から聴取できる
+    pub plus_in_area: bool,
+    /// タイムフリー30でエリア外から聴取できる
+    pub plus_out_of_area: bool,
+}
+
+impl Default for TimefreeAvailability {
+    fn default() -> Self {
+        Self {
+            in_area: true,
+            out_of_area: true,
+            plus_in_area: true,
+            plus_out_of_area: true,
+        }
+    }
+}
+
+impl TimefreeAvailability {
+    fn from_ng_flags(
+        ts_in_ng: Option<u8>,
+        ts_out_ng: Option<u8>,
+        tsplus_in_ng: Option<u8>,
+        tsplus_out_ng: Option<u8>,
+    ) -> Self {
+        let available = |ng: Option<u8>| ng.unwrap_or_default() == 0;
+        Self {
+            in_area: available(ts_in_ng),
+            out_of_area: available(ts_out_ng),
+            plus_in_area: available(tsplus_in_ng),
+            plus_out_of_area: available(tsplus_out_ng),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -130,53 +231,347 @@ impl Program {
         }
         Some(duration as u64)
     }
+
+    /// 番組が属する放送日。深夜0時〜5時に始まる番組は前日になる
+    pub fn broadcast_day(&self) -> BroadcastDay {
+        self.program_date
+            .map(BroadcastDay::new)
+            .unwrap_or_else(|| BroadcastDay::of(&self.start_time))
+    }
+
+    /// 開始時刻の`25:30`のような30時間制の表記
+    pub fn start_time_30h(&self) -> String {
+        self.broadcast_day().format_30h(&self.start_time)
+    }
+
+    /// 終了時刻の`25:30`のような30時間制の表記
+    pub fn end_time_30h(&self) -> String {
+        self.broadcast_day().format_30h(&self.end_time)
+    }
+
+    /// `start_time_s`の`2530`のような表記を日時にする
+    pub fn parse_start_time_s(&self) -> Result<DateTime<Tz>> {
+        self.broadcast_day().parse_hhmm(&self.start_time_s)
+    }
+
+    /// `end_time_s`の`2530`のような表記を日時にする
+    pub fn parse_end_time_s(&self) -> Result<DateTime<Tz>> {
+        self.broadcast_day().parse_hhmm(&self.end_time_s)
+    }
 }
 
-impl From<ProgramXml> for Program {
-    fn from(value: ProgramXml) -> Self {
-        let ft = Tokyo
-            .from_local_datetime(
-                &NaiveDateTime::parse_from_str(&value.ft, "%Y%m%d%H%M%S")
-                    .expect("time parse error"),
-            )
-            .unwrap();
-        let to = Tokyo
-            .from_local_datetime(
-                &NaiveDateTime::parse_from_str(&value.to, "%Y%m%d%H%M%S")
-                    .expect("time parse error"),
-            )
-            .unwrap();
+#[cfg(test)]
+impl Program {
+    /// テスト用のTBSの番組。必要なフィールドだけ構造体更新構文で上書きして使う
+    pub(crate) fn fixture(start_time: DateTime<Tz>, end_time: DateTime<Tz>) -> Self {
         Program {
+            start_time,
+            end_time,
+            start_time_s: "".to_string(),
+            end_time_s: "".to_string(),
+            station_id: "TBS".to_string(),
+            performer: "".to_string(),
+            title: "".to_string(),
+            info: "".to_string(),
+            description: "".to_string(),
+            img: "".to_string(),
+            program_id: None,
+            master_id: None,
+            duration: duration_between(start_time, end_time),
+            url: None,
+            url_link: None,
+            program_date: None,
+            tags: Vec::new(),
+            genres: Default::default(),
+            metas: Vec::new(),
+            timefree: Default::default(),
+            failed_record: false,
+        }
+    }
+}
+
+impl Programs {
+    /// 指定した放送日に属する番組
+    pub fn on_broadcast_day(&self, day: BroadcastDay) -> impl Iterator<Item = &Program> {
+        self.data
+            .iter()
+            .filter(move |program| program.broadcast_day() == day)
+    }
+
+    /// 指定した日時に放送局で放送中の番組
+    pub fn at<T: TimeZone>(&self, station_id: &str, instant: &DateTime<T>) -> Option<&Program> {
+        self.data.iter().find(|program| {
+            program.station_id == station_id
+                && program.start_time <= *instant
+                && *instant < program.end_time
+        })
+    }
+
+    /// 指定した日時より後に放送局で始まる最初の番組
+    pub fn next_after<T: TimeZone>(
+        &self,
+        station_id: &str,
+        instant: &DateTime<T>,
+    ) -> Option<&Program> {
+        self.data
+            .iter()
+            .filter(|program| program.station_id == station_id && program.start_time > *instant)
+            .min_by_key(|program| program.start_time)
+    }
+
+    /// 期間と放送時間が重なる番組を開始日時順に返す
+    pub fn overlapping(&self, range: Range<DateTime<Tz>>) -> Vec<&Program> {
+        let mut programs: Vec<_> = self
+            .data
+            .iter()
+            .filter(|program| program.start_time < range.end && range.start < program.end_time)
+            .collect();
+        programs.sort_by(|a, b| compare_programs(a, b));
+        programs
+    }
+
+    /// 放送局ごとに開始日時順に並べた番組
+    pub fn group_by_station(&self) -> BTreeMap<&str, Vec<&Program>> {
+        let mut stations: BTreeMap<&str, Vec<&Program>> = BTreeMap::new();
+        for program in self.sorted() {
+            stations
+                .entry(program.station_id.as_str())
+                .or_default()
+                .push(program);
+        }
+        stations
+    }
+
+    /// 開始日時、放送局IDの順に並べた番組
+    pub fn sorted(&self) -> impl Iterator<Item = &Program> + use<'_> {
+        let mut programs: Vec<_> = self.data.iter().collect();
+        programs.sort_by(|a, b| compare_programs(a, b));
+        programs.into_iter()
+    }
+
+    /// 開始日時、放送局IDの順に並べ替える
+    pub fn sort(&mut self) {
+        self.data.sort_by(compare_programs);
+    }
+}
+
+fn compare_programs(a: &Program, b: &Program) -> std::cmp::Ordering {
+    a.start_time
+        .cmp(&b.start_time)
+        .then_with(|| a.station_id.cmp(&b.station_id))
+}
+
+impl TryFrom<ProgramXml> for Program {
+    type Error = RadikoError;
+
+    fn try_from(value: ProgramXml) -> Result<Self> {
+        let ft = parse_jst_datetime(&value.ft, PROGRAM_XML_DATETIME_FORMAT)?;
+        let to = parse_jst_datetime(&value.to, PROGRAM_XML_DATETIME_FORMAT)?;
+        Ok(Program {
             start_time: ft,
             end_time: to,
-            start_time_s: value.ftl.clone(),
-            end_time_s: value.tol.clone(),
+            start_time_s: value.ftl,
+            end_time_s: value.tol,
             station_id: "".to_string(),
             performer: value.pfm.unwrap_or_default(),
-            title: value.title.clone(),
+            title: value.title,
             info: value.info.unwrap_or_default(),
             description: value.desc.unwrap_or_default(),
             img: value.img.unwrap_or_default(),
+            program_id: non_empty(Some(value.id)),
+            master_id: non_empty(value.master_id),
+            duration: value
+                .dur
+                .map(|dur| Duration::from_secs(dur.into()))
+                .unwrap_or_else(|| duration_between(ft, to)),
+            url: non_empty(value.url),
+            url_link: non_empty(value.url_link),
+            program_date: Some(BroadcastDay::of(&ft).date()),
+            tags: value
+                .tag
+                .map(|tag| tag.items.into_iter().map(|item| item.name).collect())
+                .unwrap_or_default(),
+            genres: value.genre.map(ProgramGenres::from).unwrap_or_default(),
+            metas: value
+                .metas
+                .map(|metas| {
+                    metas
+                        .metas
+                        .into_iter()
+                        .map(|meta| ProgramMeta {
+                            name: meta.name,
+                            value: meta.value,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            timefree: TimefreeAvailability::from_ng_flags(
+                value.ts_in_ng,
+                value.ts_out_ng,
+                value.tsplus_in_ng,
+                value.tsplus_out_ng,
+            ),
+            failed_record: value.failed_record.unwrap_or_default() != 0,
+        })
+    }
+}
+
+impl TryFrom<ProgramJson> for Program {
+    type Error = RadikoError;
+
+    fn try_from(value: ProgramJson) -> Result<Self> {
+        let start_time = parse_jst_datetime(&value.start_time, PROGRAM_JSON_DATETIME_FORMAT)?;
+        let end_time = parse_jst_datetime(&value.end_time, PROGRAM_JSON_DATETIME_FORMAT)?;
+        Ok(Program {
+            start_time,
+            end_time,
+            start_time_s: value.start_time_s,
+            end_time_s: value.end_time_s,
+            station_id: value.station_id,
+            performer: value.performer.unwrap_or_default(),
+            title: value.title,
+            info: value.info.unwrap_or_default(),
+            description: value.description.unwrap_or_default(),
+            img: value.img.unwrap_or_default(),
+            program_id: non_empty(value.id),
+            master_id: None,
+            duration: duration_between(start_time, end_time),
+            url: non_empty(value.program_url),
+            url_link: None,
+            program_date: Some(
+                non_empty(value.program_date)
+                    .as_deref()
+                    .map(parse_program_date)
+                    .transpose()?
+                    .unwrap_or_else(|| BroadcastDay::of(&start_time).date()),
+            ),
+            tags: Vec::new(),
+            genres: ProgramGenres::from(value.genre),
+            metas: value
+                .metas
+                .into_iter()
+                .map(|meta| ProgramMeta {
+                    name: meta.name,
+                    value: meta.value,
+                })
+                .collect(),
+            timefree: TimefreeAvailability::from_ng_flags(
+                value.ts_in_ng,
+                value.ts_out_ng,
+                value.tsplus_in_ng,
+                value.tsplus_out_ng,
+            ),
+            failed_record: false,
+        })
+    }
+}
+
+impl From<GenreXml> for ProgramGenres {
+    fn from(value: GenreXml) -> Self {
+        Self {
+            program: value
+                .programs
+                .into_iter()
+                .map(|genre| Genre {
+                    id: genre.id,
+                    name: genre.name,
+                })
+                .collect(),
+            personality: value
+                .personalities
+                .into_iter()
+                .map(|genre| Genre {
+                    id: genre.id,
+                    name: genre.name,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<GenreJson> for ProgramGenres {
+    fn from(value: GenreJson) -> Self {
+        let genre = |genre: GenreItemJson| Genre {
+            id: genre.id,
+            name: genre.name,
+        };
+        Self {
+            program: value.program.into_iter().map(genre).collect(),
+            personality: value.personality.into_iter().map(genre).collect(),
         }
     }
 }
 
-impl From<RadikoProgramXml> for Programs {
-    fn from(value: RadikoProgramXml) -> Self {
+impl TryFrom<RadikoProgramXml> for Programs {
+    type Error = RadikoError;
+
+    fn try_from(value: RadikoProgramXml) -> Result<Self> {
         let mut programs = Vec::new();
         for station in value.stations.station {
             for programs_xml in station.programs {
+                let program_date = programs_xml
+                    .date
+                    .as_deref()
+                    .map(parse_program_date)
+                    .transpose()?;
                 for program_xml in programs_xml.program {
-                    let mut program = Program::from(program_xml);
+                    let mut program = Program::try_from(program_xml)?;
                     program.station_id = station.id.clone();
+                    if program_date.is_some() {
+                        program.program_date = program_date;
+                    }
                     programs.push(program);
                 }
             }
         }
-        Programs { data: programs }
+        Ok(Programs { data: programs })
+    }
+}
+
+impl TryFrom<SearchResultJson> for Programs {
+    type Error = RadikoError;
+
+    fn try_from(value: SearchResultJson) -> Result<Self> {
+        Ok(Programs {
+            data: value
+                .data
+                .into_iter()
+                .map(Program::try_from)
+                .collect::<Result<_>>()?,
+        })
     }
 }
 
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.is_empty())
+}
+
+fn duration_between(start_time: DateTime<Tz>, end_time: DateTime<Tz>) -> Duration {
+    end_time
+        .signed_duration_since(start_time)
+        .to_std()
+        .unwrap_or_default()
+}
+
+/// 放送日の`20250628`形式の日付
+fn parse_program_date(value: &str) -> Result<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(value, "%Y%m%d")?)
+}
+
+/// 番組表XMLの日時の形式
+const PROGRAM_XML_DATETIME_FORMAT: &str = "%Y%m%d%H%M%S";
+/// 検索APIの日時の形式
+const PROGRAM_JSON_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 日時をJSTとして解釈する
+fn parse_jst_datetime(value: &str, format: &str) -> Result<DateTime<Tz>> {
+    let naive = NaiveDateTime::parse_from_str(value, format)?;
+    Tokyo
+        .from_local_datetime(&naive)
+        .single()
+        .ok_or_else(|| RadikoError::DateTimeParse(format!("ambiguous datetime: {}", value)))
+}
+
 /// https://serde.rs/custom-date-format.html
 mod jst_datetime {
     use chrono::{DateTime, NaiveDateTime, TimeZone};
@@ -212,7 +607,136 @@ mod jst_datetime {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let dt = NaiveDateTime::parse_from_str(&s, FORMAT).unwrap();
-        Ok(Tokyo.from_local_datetime(&dt).unwrap())
+        let dt = NaiveDateTime::parse_from_str(&s, FORMAT).map_err(serde::de::Error::custom)?;
+        Tokyo
+            .from_local_datetime(&dt)
+            .single()
+            .ok_or_else(|| serde::de::Error::custom(format!("ambiguous datetime: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_from_xml_test() -> anyhow::Result<()> {
+        let radiko_program: RadikoProgramXml =
+            quick_xml::de::from_str(include_str!("../../examples/radiko/TBS.xml"))?;
+
+        let programs = Programs::try_from(radiko_program)?;
+
+        let program = &programs.data[0];
+        assert_eq!(program.station_id, "TBS");
+        assert_eq!(program.program_id.as_deref(), Some("11786318"));
+        assert_eq!(program.master_id, None);
+        assert_eq!(program.duration, Duration::from_secs(300));
+        assert_eq!(program.url, None);
+        assert_eq!(program.program_date, NaiveDate::from_ymd_opt(2025, 6, 22));
+        assert_eq!(
+            program.tags,
+            vec!["政治・経済を学べる", "朝のニュースを効率良く"]
+        );
+        assert_eq!(
+            program.genres.program,
+            vec![Genre {
+                id: "P001".to_string(),
+                name: "ニュース/天気/交通".to_string()
+            }]
+        );
+        assert!(program.genres.personality.is_empty());
+        assert_eq!(
+            program.metas,
+            vec![ProgramMeta {
+                name: "twitter".to_string(),
+                value: "#radiko".to_string()
+            }]
+        );
+        assert_eq!(program.timefree, TimefreeAvailability::default());
+        assert!(!program.failed_record);
+        assert_eq!(
+            programs.data[1].url_link.as_deref(),
+            Some("https://www.tbsradio.jp/seri954/?x11=_(radiko-uid)")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn program_from_search_json_test() -> anyhow::Result<()> {
+        let search_result: SearchResultJson =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+
+        let programs = Programs::try_from(search_result)?;
+
+        let program = &programs.data[0];
+        assert_eq!(program.station_id, "MBS");
+        assert_eq!(program.duration, Duration::from_secs(90 * 60));
+        assert_eq!(
+            program.url.as_deref(),
+            Some("https://www.mbs1179.com/yaru/")
+        );
+        // 深夜0時の番組は前日の放送日に属する
+        assert_eq!(program.program_date, NaiveDate::from_ymd_opt(2025, 6, 28));
+        assert_eq!(program.broadcast_day().to_string(), "20250628");
+        assert_eq!(program.start_time_s, "2400");
+        assert_eq!(program.start_time_30h(), "24:00");
+        assert_eq!(program.end_time_30h(), "25:30");
+        assert_eq!(program.parse_start_time_s()?, program.start_time);
+        assert_eq!(program.parse_end_time_s()?, program.end_time);
+        assert_eq!(
+            programs
+                .on_broadcast_day(BroadcastDay::of(&program.start_time))
+                .count(),
+            programs
+                .data
+                .iter()
+                .filter(|other| other.program_date == program.program_date)
+                .count()
+        );
+        assert_eq!(program.genres.program[0].id, "P006");
+        assert_eq!(program.genres.personality[0].name, "タレント");
+        assert_eq!(program.metas[0].value, "#radiko");
+        assert!(program.timefree.in_area);
+        assert!(
+            programs
+                .data
+                .iter()
+                .any(|program| program.genres == ProgramGenres::default())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn programs_query_test() -> anyhow::Result<()> {
+        let radiko_program: RadikoProgramXml =
+            quick_xml::de::from_str(include_str!("../../examples/radiko/TBS.xml"))?;
+        let programs = Programs::try_from(radiko_program)?;
+        let day: BroadcastDay = "20250622".parse()?;
+        let at = day.parse_hhmm("2500")?;
+
+        let program = programs.at("TBS", &at).unwrap();
+        assert_eq!(program.program_id.as_deref(), Some("11786350"));
+        assert_eq!(program.start_time_30h(), "25:00");
+        assert!(programs.at("QRR", &at).is_none());
+        // 番組の開始日時ちょうどは次の番組に含まれない
+        let next = programs.next_after("TBS", &program.start_time).unwrap();
+        assert_eq!(next.start_time, program.end_time);
+
+        let overlapping = programs.overlapping(day.parse_hhmm("2430")?..day.parse_hhmm("2501")?);
+        let ids: Vec<_> = overlapping
+            .iter()
+            .map(|program| program.program_id.as_deref().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["11786349", "11786350"]);
+
+        let stations = programs.group_by_station();
+        assert_eq!(stations.keys().collect::<Vec<_>>(), vec![&"TBS"]);
+        assert!(
+            stations["TBS"]
+                .windows(2)
+                .all(|pair| pair[0].start_time <= pair[1].start_time)
+        );
+        assert_eq!(programs.sorted().count(), programs.data.len());
+        Ok(())
     }
 }
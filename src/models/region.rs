@@ -2,7 +2,7 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::dto::region_xml::{RegionStationXml, RegionStationsXml, RegionXml};
 
-use super::logo::Logo;
+use super::{area::AreaRegion, logo::Logo};
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Region {
     pub stations_groups: Vec<RegionStations>,
@@ -32,6 +32,13 @@ pub struct RegionStation {
     pub simul_max_delay: u32,
 }
 
+impl RegionStations {
+    /// 全国放送の場合はNone
+    pub fn region(&self) -> Option<AreaRegion> {
+        AreaRegion::from_region_id(&self.region_id)
+    }
+}
+
 impl From<RegionStationXml> for RegionStation {
     fn from(value: RegionStationXml) -> Self {
         Self {
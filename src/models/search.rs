@@ -1,8 +1,20 @@
+use chrono::NaiveDate;
 use serde_derive::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use strum_macros::{AsRefStr, Display};
+use strum_macros::{AsRefStr, Display, EnumString};
 
-#[derive(Debug, Clone, Copy, Display, AsRefStr, Serialize, Deserialize)]
+use crate::{
+    dto::search_json::{SearchMetaJson, SearchResultJson},
+    error::{RadikoError, Result},
+    models::{broadcast_day::BroadcastDay, program::Program},
+};
+
+/// 検索APIの`start_day`、`end_day`の形式
+const SEARCH_DAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Display, AsRefStr, EnumString, Serialize, Deserialize,
+)]
 pub enum Filter {
     #[strum(to_string = "future")]
     Live,
@@ -38,12 +50,25 @@ pub enum Filter {
 pub struct SearchCondition {
     pub key: Vec<String>,
     pub filter: Option<Filter>,
-    pub start_day: Option<String>,
-    pub end_day: Option<String>,
+    /// 放送日で絞り込む
+    pub start_day: Option<BroadcastDay>,
+    pub end_day: Option<BroadcastDay>,
+    /// 1ページあたりの件数
     pub row_limit: Option<i32>,
+    /// 0から始まるページ番号
+    pub page_idx: Option<u32>,
+    /// `Area::Tokyo.into()`のように`Area`からも指定できる
     pub area_id: Option<Vec<String>>,
+    /// `AreaRegion::Kanto.as_ref()`のような地方ID
+    pub region_id: Option<String>,
     pub station_id: Option<Vec<String>>,
     pub cur_area_id: Option<String>,
+    /// 番組のジャンルは`P006`、出演者のジャンルは`C010`のような形式
+    pub genre_id: Option<Vec<String>>,
+    /// 検索の拡張条件。値はそのまま送信する
+    pub kakuchou: Option<Vec<String>>,
+    /// 検索語の候補。値はそのまま送信する
+    pub suisengo: Option<Vec<String>>,
 }
 
 impl Default for SearchCondition {
@@ -54,9 +79,14 @@ impl Default for SearchCondition {
             key: Default::default(),
             start_day: Default::default(),
             end_day: Default::default(),
+            page_idx: Default::default(),
             area_id: Default::default(),
+            region_id: Default::default(),
             station_id: Default::default(),
             cur_area_id: Default::default(),
+            genre_id: Default::default(),
+            kakuchou: Default::default(),
+            suisengo: Default::default(),
         }
     }
 }
@@ -81,16 +111,44 @@ impl SearchCondition {
             }
         }
 
+        if let Some(region_id) = &self.region_id {
+            params.push(("region_id".to_string(), region_id.clone()));
+        }
+
         if let Some(cur_area_id) = &self.cur_area_id {
             params.push(("cur_area_id".to_string(), cur_area_id.clone()));
         }
 
+        if let Some(genre_ids) = &self.genre_id {
+            for genre_id in genre_ids {
+                params.push(("genre_id".to_string(), genre_id.clone()));
+            }
+        }
+
+        if let Some(kakuchou) = &self.kakuchou {
+            for value in kakuchou {
+                params.push(("kakuchou".to_string(), value.clone()));
+            }
+        }
+
+        if let Some(suisengo) = &self.suisengo {
+            for value in suisengo {
+                params.push(("suisengo".to_string(), value.clone()));
+            }
+        }
+
         if let Some(start_day) = &self.start_day {
-            params.push(("start_day".to_string(), start_day.clone()));
+            params.push((
+                "start_day".to_string(),
+                start_day.date().format(SEARCH_DAY_FORMAT).to_string(),
+            ));
         }
 
         if let Some(end_day) = &self.end_day {
-            params.push(("end_day".to_string(), end_day.clone()));
+            params.push((
+                "end_day".to_string(),
+                end_day.date().format(SEARCH_DAY_FORMAT).to_string(),
+            ));
         }
 
         if let Some(filter) = &self.filter {
@@ -101,6 +159,217 @@ impl SearchCondition {
             params.push(("row_limit".to_string(), row_limit.to_string()));
         }
 
+        if let Some(page_idx) = &self.page_idx {
+            params.push(("page_idx".to_string(), page_idx.to_string()));
+        }
+
         params
     }
 }
+
+/// 検索結果の1ページ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub meta: SearchMeta,
+    pub data: Vec<Program>,
+}
+
+/// 検索APIが解釈した検索条件と件数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchMeta {
+    pub key: Vec<String>,
+    pub station_id: Vec<String>,
+    pub area_id: Vec<String>,
+    pub cur_area_id: Option<String>,
+    pub region_id: Option<String>,
+    pub start_day: Option<BroadcastDay>,
+    pub end_day: Option<BroadcastDay>,
+    pub filter: Option<Filter>,
+    /// 全てのページを合わせた件数
+    pub result_count: u32,
+    pub page_idx: u32,
+    pub row_limit: u32,
+    pub kakuchou: Vec<String>,
+    /// 検索語の候補
+    pub suisengo: Option<String>,
+    pub genre_id: Vec<String>,
+}
+
+impl SearchResult {
+    /// このページまでの件数が全体の件数に満たない場合にtrue
+    /// 1ページあたりの件数が分からなければfalse
+    pub fn has_next_page(&self, condition: &SearchCondition) -> bool {
+        let Some(row_limit) = self.row_limit(condition) else {
+            return false;
+        };
+        !self.data.is_empty()
+            && self.meta.page_idx as usize * row_limit as usize + self.data.len()
+                < self.meta.result_count as usize
+    }
+
+    /// 全体の件数と1ページあたりの件数から求めたページ数
+    pub fn page_count(&self, condition: &SearchCondition) -> Option<u32> {
+        self.row_limit(condition)
+            .map(|row_limit| self.meta.result_count.div_ceil(row_limit))
+    }
+
+    /// 1ページあたりの件数。メタ情報に無い場合は検索条件の`row_limit`を使う
+    fn row_limit(&self, condition: &SearchCondition) -> Option<u32> {
+        Some(self.meta.row_limit)
+            .filter(|row_limit| *row_limit > 0)
+            .or_else(|| {
+                condition
+                    .row_limit
+                    .and_then(|row_limit| u32::try_from(row_limit).ok())
+                    .filter(|row_limit| *row_limit > 0)
+            })
+    }
+}
+
+impl TryFrom<SearchResultJson> for SearchResult {
+    type Error = RadikoError;
+
+    fn try_from(value: SearchResultJson) -> Result<Self> {
+        Ok(SearchResult {
+            meta: SearchMeta::from(value.meta),
+            data: value
+                .data
+                .into_iter()
+                .map(Program::try_from)
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// メタ情報は参考情報なので、解釈できない値は無視して検索結果自体は返す
+impl From<SearchMetaJson> for SearchMeta {
+    fn from(value: SearchMetaJson) -> Self {
+        SearchMeta {
+            key: value.key,
+            station_id: value.station_id,
+            area_id: value.area_id,
+            cur_area_id: non_empty(value.cur_area_id),
+            region_id: non_empty(value.region_id),
+            start_day: parse_search_day(&value.start_day),
+            end_day: parse_search_day(&value.end_day),
+            filter: value.filter.parse().ok(),
+            result_count: value.result_count,
+            page_idx: value.page_idx,
+            row_limit: value.row_limit,
+            kakuchou: value.kakuchou,
+            suisengo: non_empty(value.suisengo),
+            genre_id: value.genre_id,
+        }
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// `2025-06-28`形式。`20250628`形式も受け付ける
+/// 空文字列や解釈できない値はNone
+fn parse_search_day(value: &str) -> Option<BroadcastDay> {
+    NaiveDate::parse_from_str(value, SEARCH_DAY_FORMAT)
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d"))
+        .ok()
+        .map(BroadcastDay::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_result_from_json_test() -> anyhow::Result<()> {
+        let search_result: SearchResultJson =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+
+        let result = SearchResult::try_from(search_result)?;
+
+        assert_eq!(result.meta.key, vec!["トム・ブラウン"]);
+        assert_eq!(result.meta.result_count, 8);
+        assert_eq!(result.meta.filter, Some(Filter::All));
+        assert_eq!(result.meta.region_id, None);
+        assert_eq!(result.meta.start_day, None);
+        assert_eq!(result.data.len(), 8);
+        assert!(!result.has_next_page(&SearchCondition::default()));
+        Ok(())
+    }
+
+    #[test]
+    fn search_meta_lenient_test() -> anyhow::Result<()> {
+        let mut search_result: serde_json::Value =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+        search_result["meta"]["start_day"] = "2025/06/28".into();
+        search_result["meta"]["end_day"] = "20250629".into();
+
+        let result =
+            SearchResult::try_from(serde_json::from_value::<SearchResultJson>(search_result)?)?;
+
+        // 解釈できないメタ情報があっても検索結果は返す
+        assert_eq!(result.meta.start_day, None);
+        assert_eq!(result.meta.end_day, Some("20250629".parse()?));
+        assert_eq!(result.data.len(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn has_next_page_test() -> anyhow::Result<()> {
+        let search_result: SearchResultJson =
+            serde_json::from_str(include_str!("../../examples/radiko/search_result.json"))?;
+        let mut result = SearchResult::try_from(search_result)?;
+        assert_eq!(result.meta.row_limit, 0);
+        let condition = SearchCondition {
+            row_limit: Some(5),
+            ..Default::default()
+        };
+
+        // メタ情報のrow_limitが0の場合は検索条件のrow_limitで判定する
+        result.data.truncate(5);
+        assert!(result.has_next_page(&condition));
+        assert_eq!(result.page_count(&condition), Some(2));
+        // 3件だけの最後のページ
+        result.meta.page_idx = 1;
+        result.data.truncate(3);
+        assert!(!result.has_next_page(&condition));
+        // 1ページあたりの件数が分からない場合は次のページを取得しない
+        result.meta.page_idx = 0;
+        let unknown_row_limit = SearchCondition {
+            row_limit: None,
+            ..Default::default()
+        };
+        assert!(!result.has_next_page(&unknown_row_limit));
+        assert_eq!(result.page_count(&unknown_row_limit), None);
+        Ok(())
+    }
+
+    #[test]
+    fn search_condition_query_params_test() {
+        let condition = SearchCondition {
+            key: vec!["ニュース".to_string()],
+            start_day: Some("20250628".parse().unwrap()),
+            region_id: Some("kanto".to_string()),
+            genre_id: Some(vec!["P001".to_string(), "C010".to_string()]),
+            page_idx: Some(2),
+            suisengo: Some(vec!["ニュース".to_string()]),
+            ..Default::default()
+        };
+
+        let params = condition.to_query_params();
+        let value = |name: &str| {
+            params
+                .iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(value("start_day"), vec!["2025-06-28"]);
+        assert!(value("end_day").is_empty());
+        assert_eq!(value("region_id"), vec!["kanto"]);
+        assert_eq!(value("genre_id"), vec!["P001", "C010"]);
+        assert_eq!(value("page_idx"), vec!["2"]);
+        assert_eq!(value("suisengo"), vec!["ニュース"]);
+        assert_eq!(value("filter"), vec!["future"]);
+    }
+}
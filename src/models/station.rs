@@ -2,7 +2,7 @@ use serde_derive::{Deserialize, Serialize};
 
 use crate::dto::station_xml::{RadikoStationXml, StationXml};
 
-use super::logo::Logo;
+use super::{logo::Logo, region::RegionStation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Station {
@@ -44,6 +44,24 @@ impl From<StationXml> for Station {
     }
 }
 
+impl From<RegionStation> for Station {
+    fn from(value: RegionStation) -> Self {
+        Station {
+            id: value.id,
+            name: value.name,
+            ascii_name: value.ascii_name,
+            ruby: value.ruby,
+            areafree: value.areafree == 1,
+            timefree: value.timefree == 1,
+            logos: value.logos,
+            banner: value.banner,
+            href: value.href,
+            simul_max_delay: value.simul_max_delay,
+            tf_max_delay: value.tf_max_delay,
+        }
+    }
+}
+
 impl From<RadikoStationXml> for Stations {
     fn from(value: RadikoStationXml) -> Self {
         Stations {
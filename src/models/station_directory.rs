@@ -0,0 +1,342 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{
+    area::{Area, AreaRegion},
+    region::{Region, RegionStations},
+    station::{Station, Stations},
+};
+
+/// 全国放送の放送局が属する地域
+const NATIONWIDE_REGION_ID: &str = "zenkoku";
+
+/// 全国の放送局一覧の索引
+/// 放送局ID、エリア、地域、名前から放送局を引けるようにする
+#[derive(Debug, Clone, Default)]
+pub struct StationDirectory {
+    entries: Vec<DirectoryStation>,
+    by_id: HashMap<String, usize>,
+    by_region: HashMap<String, Vec<usize>>,
+}
+
+/// 索引に登録された放送局
+#[derive(Debug, Clone)]
+pub struct DirectoryStation {
+    pub station: Station,
+    /// 放送局一覧の地域。全国放送の場合は`zenkoku`
+    pub region_id: String,
+    /// 放送局の所在エリア
+    pub area_id: String,
+    /// ライブで聴取できるエリア
+    /// 全国放送は全エリア、それ以外は所在エリアと`add_area_stations`で登録したエリア
+    pub areas: BTreeSet<Area>,
+}
+
+/// 放送局を聴取できるかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StationAvailability {
+    /// エリア内の放送局としてライブで聴取できる
+    Live,
+    /// エリア外のためエリアフリー(プレミアム会員)でのみ聴取できる
+    AreaFree,
+    /// エリア外でエリアフリーにも対応していない
+    Unavailable,
+}
+
+impl DirectoryStation {
+    pub fn is_nationwide(&self) -> bool {
+        self.region_id == NATIONWIDE_REGION_ID
+    }
+
+    pub fn region(&self) -> Option<AreaRegion> {
+        AreaRegion::from_region_id(&self.region_id)
+    }
+}
+
+impl StationDirectory {
+    pub fn new(region_stations: Vec<RegionStations>) -> Self {
+        let mut directory = Self::default();
+        for group in region_stations {
+            for station in group.stations {
+                let mut areas = BTreeSet::new();
+                if group.region_id == NATIONWIDE_REGION_ID {
+                    areas.extend(Area::all());
+                } else if let Ok(area) = station.area_id.parse() {
+                    areas.insert(area);
+                }
+                let area_id = station.area_id.clone();
+                directory.insert(DirectoryStation {
+                    station: Station::from(station),
+                    region_id: group.region_id.clone(),
+                    area_id,
+                    areas,
+                });
+            }
+        }
+        directory
+    }
+
+    fn insert(&mut self, entry: DirectoryStation) {
+        // 同じ放送局が複数の地域に含まれている場合は最初のものを使う
+        if self.by_id.contains_key(&entry.station.id) {
+            return;
+        }
+        let index = self.entries.len();
+        self.by_id.insert(entry.station.id.clone(), index);
+        self.by_region
+            .entry(entry.region_id.clone())
+            .or_default()
+            .push(index);
+        self.entries.push(entry);
+    }
+
+    /// エリアごとの放送局一覧(`stations_from_area_id`の結果)から、そのエリアで聴取できる放送局を登録する
+    /// 全国の放送局一覧には所在エリアしか含まれないため、複数の県で聴取できる放送局はこれで補う
+    pub fn add_area_stations(&mut self, stations: &Stations) {
+        let Ok(area) = stations.area_id.parse::<Area>() else {
+            return;
+        };
+        for station in &stations.data {
+            if let Some(&index) = self.by_id.get(&station.id) {
+                self.entries[index].areas.insert(area);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DirectoryStation> {
+        self.entries.iter()
+    }
+
+    pub fn get(&self, station_id: &str) -> Option<&DirectoryStation> {
+        self.by_id
+            .get(station_id)
+            .map(|&index| &self.entries[index])
+    }
+
+    pub fn station(&self, station_id: &str) -> Option<&Station> {
+        self.get(station_id).map(|entry| &entry.station)
+    }
+
+    /// 放送局をライブで聴取できるエリア
+    pub fn areas_of(&self, station_id: &str) -> Vec<Area> {
+        self.get(station_id)
+            .map(|entry| entry.areas.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// エリアでライブで聴取できる放送局
+    /// area_idには`"JP13"`の他に`Area::Tokyo`も渡せる
+    pub fn in_area(&self, area_id: impl AsRef<str>) -> Vec<&DirectoryStation> {
+        let Ok(area) = area_id.as_ref().parse::<Area>() else {
+            return Vec::new();
+        };
+        self.entries
+            .iter()
+            .filter(|entry| entry.areas.contains(&area))
+            .collect()
+    }
+
+    /// 放送局一覧の地域に含まれる放送局
+    /// region_idには`"kanto"`や`"zenkoku"`の他に`AreaRegion::Kanto`も渡せる
+    pub fn in_region(&self, region_id: impl AsRef<str>) -> Vec<&DirectoryStation> {
+        self.by_region
+            .get(region_id.as_ref())
+            .map(|indexes| indexes.iter().map(|&index| &self.entries[index]).collect())
+            .unwrap_or_default()
+    }
+
+    /// 放送局ID、名前、ASCII表記の名前、読み仮名から放送局を曖昧検索する
+    /// 全角半角、大文字小文字、カタカナとひらがな、空白と記号の違いは無視し、
+    /// 完全一致、前方一致、部分一致、文字を順に含むものの順に返す
+    pub fn search(&self, query: &str) -> Vec<&DirectoryStation> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut matches = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let station = &entry.station;
+                [
+                    &station.id,
+                    &station.name,
+                    &station.ascii_name,
+                    &station.ruby,
+                ]
+                .into_iter()
+                .filter_map(|field| match_rank(&normalize(field), &query))
+                .min()
+                .map(|rank| (rank, index))
+            })
+            .collect::<Vec<_>>();
+        matches.sort();
+        matches
+            .into_iter()
+            .map(|(_, index)| &self.entries[index])
+            .collect()
+    }
+
+    /// エリアから放送局を聴取できるかどうか
+    /// 索引に無い放送局の場合はNone
+    pub fn availability(
+        &self,
+        station_id: &str,
+        area_id: impl AsRef<str>,
+    ) -> Option<StationAvailability> {
+        let entry = self.get(station_id)?;
+        let in_area = area_id
+            .as_ref()
+            .parse::<Area>()
+            .is_ok_and(|area| entry.areas.contains(&area));
+        Some(if in_area {
+            StationAvailability::Live
+        } else if entry.station.areafree {
+            StationAvailability::AreaFree
+        } else {
+            StationAvailability::Unavailable
+        })
+    }
+}
+
+impl From<Region> for StationDirectory {
+    fn from(region: Region) -> Self {
+        Self::new(region.stations_groups)
+    }
+}
+
+impl From<Vec<RegionStations>> for StationDirectory {
+    fn from(region_stations: Vec<RegionStations>) -> Self {
+        Self::new(region_stations)
+    }
+}
+
+/// 検索のために表記揺れを取り除く
+fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            // カタカナはひらがなにする
+            '\u{30a1}'..='\u{30f6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            c => c,
+        })
+        .filter(|c| c.is_alphanumeric() || *c == 'ー')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 一致の度合い。小さいほど近い
+fn match_rank(value: &str, query: &str) -> Option<u8> {
+    if value == query {
+        Some(0)
+    } else if value.starts_with(query) {
+        Some(1)
+    } else if value.contains(query) {
+        Some(2)
+    } else {
+        let mut chars = value.chars();
+        query.chars().all(|q| chars.any(|c| c == q)).then_some(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::{region_xml::RegionXml, station_xml::RadikoStationXml};
+
+    const REGION_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<region>
+  <stations ascii_name="ZENKOKU" region_id="zenkoku" region_name="全国">
+    <station><id>RN1</id><name>ラジオNIKKEI第1</name><ascii_name>RADIONIKKEI</ascii_name><ruby>らじおにっけいだいいち</ruby><areafree>1</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/RN1/224x100.png</logo><tf_max_delay>15</tf_max_delay><banner>https://radiko.jp/res/banner/RN1/20150311182807.png</banner><area_id>JP13</area_id><href>http://www.radionikkei.jp/</href><simul_max_delay>15</simul_max_delay></station>
+  </stations>
+  <stations ascii_name="KANTO" region_id="kanto" region_name="関東">
+    <station><id>TBS</id><name>TBSラジオ</name><ascii_name>TBS RADIO</ascii_name><ruby>てぃーびーえすらじお</ruby><areafree>1</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/TBS/224x100.png</logo><tf_max_delay>15</tf_max_delay><banner>https://radiko.jp/res/banner/TBS/20220308173224.png</banner><area_id>JP13</area_id><href>https://www.tbsradio.jp/</href><simul_max_delay>15</simul_max_delay></station>
+    <station><id>LFR</id><name>ニッポン放送</name><ascii_name>NIPPON BROADCASTING</ascii_name><ruby>にっぽんほうそう</ruby><areafree>1</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/LFR/224x100.png</logo><tf_max_delay>15</tf_max_delay><banner>https://radiko.jp/res/banner/LFR/20161220193014.png</banner><area_id>JP13</area_id><href>https://www.allnightnippon.com/</href><simul_max_delay>15</simul_max_delay></station>
+  </stations>
+  <stations ascii_name="KINKI" region_id="kinki" region_name="近畿">
+    <station><id>MBS</id><name>MBSラジオ</name><ascii_name>MBS RADIO</ascii_name><ruby>えむびーえすらじお</ruby><areafree>1</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/MBS/224x100.png</logo><tf_max_delay>15</tf_max_delay><banner>https://radiko.jp/res/banner/MBS/20200401120000.png</banner><area_id>JP27</area_id><href>https://www.mbs1179.com/</href><simul_max_delay>15</simul_max_delay></station>
+    <station><id>KISSFMKOBE</id><name>Kiss FM KOBE</name><ascii_name>Kiss FM KOBE</ascii_name><ruby>きっすえふえむこうべ</ruby><areafree>0</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/KISSFMKOBE/224x100.png</logo><tf_max_delay>15</tf_max_delay><banner>https://radiko.jp/res/banner/KISSFMKOBE/20200401120000.png</banner><area_id>JP28</area_id><href>https://www.kiss-fm.co.jp/</href><simul_max_delay>15</simul_max_delay></station>
+  </stations>
+</region>"#;
+
+    fn ids(entries: Vec<&DirectoryStation>) -> Vec<&str> {
+        entries
+            .into_iter()
+            .map(|entry| entry.station.id.as_str())
+            .collect()
+    }
+
+    fn directory() -> StationDirectory {
+        let region: RegionXml = quick_xml::de::from_str(REGION_XML).unwrap();
+        StationDirectory::from(Region::from(region))
+    }
+
+    #[test]
+    fn station_directory_index_test() {
+        let mut directory = directory();
+
+        assert_eq!(directory.len(), 5);
+        assert_eq!(directory.station("LFR").unwrap().name, "ニッポン放送");
+        assert_eq!(directory.areas_of("MBS"), vec![Area::Osaka]);
+        assert_eq!(directory.areas_of("RN1").len(), 47);
+        assert!(directory.get("RN1").unwrap().is_nationwide());
+        assert_eq!(
+            directory.get("TBS").unwrap().region(),
+            Some(AreaRegion::Kanto)
+        );
+        assert_eq!(
+            ids(directory.in_region(AreaRegion::Kinki)),
+            vec!["MBS", "KISSFMKOBE"]
+        );
+        assert_eq!(
+            ids(directory.in_area(Area::Tokyo)),
+            vec!["RN1", "TBS", "LFR"]
+        );
+
+        // 京都の放送局一覧に含まれていればMBSは京都でも聴取できる
+        let kyoto: RadikoStationXml = quick_xml::de::from_str(
+            r#"<stations area_id="JP26" area_name="KYOTO JAPAN"><station><id>MBS</id><name>MBSラジオ</name><ascii_name>MBS RADIO</ascii_name><ruby>えむびーえすらじお</ruby><areafree>1</areafree><timefree>1</timefree><logo width="224" height="100" align="center">https://radiko.jp/v2/static/station/logo/MBS/224x100.png</logo><banner>https://radiko.jp/res/banner/MBS/20200401120000.png</banner><href>https://www.mbs1179.com/</href><simul_max_delay>15</simul_max_delay><tf_max_delay>15</tf_max_delay></station></stations>"#,
+        )
+        .unwrap();
+        directory.add_area_stations(&Stations::from(kyoto));
+        assert_eq!(directory.areas_of("MBS"), vec![Area::Kyoto, Area::Osaka]);
+
+        assert_eq!(
+            directory.availability("MBS", "JP26"),
+            Some(StationAvailability::Live)
+        );
+        assert_eq!(
+            directory.availability("MBS", Area::Tokyo),
+            Some(StationAvailability::AreaFree)
+        );
+        assert_eq!(
+            directory.availability("KISSFMKOBE", Area::Tokyo),
+            Some(StationAvailability::Unavailable)
+        );
+        assert_eq!(directory.availability("UNKNOWN", Area::Tokyo), None);
+    }
+
+    #[test]
+    fn search_station_test() {
+        let directory = directory();
+        let search = |query: &str| ids(directory.search(query));
+
+        assert_eq!(search("tbs"), vec!["TBS"]);
+        assert_eq!(search("ＴＢＳラジオ"), vec!["TBS"]);
+        assert_eq!(search("ニッポン"), vec!["LFR"]);
+        assert_eq!(search("にっぽんほうそう"), vec!["LFR"]);
+        assert_eq!(search("kiss fm"), vec!["KISSFMKOBE"]);
+        assert_eq!(search("らじお"), vec!["RN1", "TBS", "MBS"]);
+        assert_eq!(search("mbsradio"), vec!["MBS"]);
+        assert!(search("").is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::dto::stream_xml::{RadikoStreamUrlsXml, StreamUrlXml};
+
+/// 放送局ごとのplaylist.m3u8の取得元
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamUrl {
+    pub playlist_create_url: String,
+    pub areafree: bool,
+    pub timefree: bool,
+    pub max_delay: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StreamUrls {
+    pub data: Vec<StreamUrl>,
+}
+
+impl StreamUrls {
+    /// 条件に一致する最初の取得元
+    /// radikoのプレイヤーと同じく、XMLに記載された順に優先する
+    pub fn find(&self, areafree: bool, timefree: bool) -> Option<&StreamUrl> {
+        self.data
+            .iter()
+            .find(|url| url.areafree == areafree && url.timefree == timefree)
+    }
+}
+
+impl From<StreamUrlXml> for StreamUrl {
+    fn from(value: StreamUrlXml) -> Self {
+        StreamUrl {
+            playlist_create_url: value.playlist_create_url,
+            areafree: value.areafree == 1,
+            timefree: value.timefree == 1,
+            max_delay: value.max_delay,
+        }
+    }
+}
+
+impl From<RadikoStreamUrlsXml> for StreamUrls {
+    fn from(value: RadikoStreamUrlsXml) -> Self {
+        StreamUrls {
+            data: value.urls.into_iter().map(StreamUrl::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STREAM_URLS_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urls>
+  <url timefree="0" areafree="0" max_delay="15">
+    <playlist_create_url>https://si-f-radiko.smartstream.ne.jp/so/playlist.m3u8</playlist_create_url>
+  </url>
+  <url timefree="0" areafree="0" max_delay="15">
+    <playlist_create_url>https://f-radiko.smartstream.ne.jp/TBS/_definst_/simul-stream.stream/playlist.m3u8</playlist_create_url>
+  </url>
+  <url timefree="0" areafree="1" max_delay="15">
+    <playlist_create_url>https://si-c-radiko.smartstream.ne.jp/so/playlist.m3u8</playlist_create_url>
+  </url>
+  <url timefree="1" areafree="0" max_delay="0">
+    <playlist_create_url>https://tf-f-rpaa-radiko.smartstream.ne.jp/tf/playlist.m3u8</playlist_create_url>
+  </url>
+</urls>
+"#;
+
+    #[test]
+    fn find_stream_url_test() -> anyhow::Result<()> {
+        let xml: RadikoStreamUrlsXml = quick_xml::de::from_str(STREAM_URLS_XML)?;
+        let stream_urls = StreamUrls::from(xml);
+
+        assert_eq!(stream_urls.data.len(), 4);
+        assert_eq!(
+            stream_urls.find(false, false).unwrap().playlist_create_url,
+            "https://si-f-radiko.smartstream.ne.jp/so/playlist.m3u8"
+        );
+        assert_eq!(
+            stream_urls.find(true, false).unwrap().playlist_create_url,
+            "https://si-c-radiko.smartstream.ne.jp/so/playlist.m3u8"
+        );
+        let timefree = stream_urls.find(false, true).unwrap();
+        assert_eq!(timefree.max_delay, 0);
+        assert!(stream_urls.find(true, true).is_none());
+        Ok(())
+    }
+}
@@ -1,141 +1,562 @@
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use chrono::DateTime;
+use chrono_tz::Tz;
+use futures_util::{Stream, TryStreamExt};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use crate::{
     api::{
-        auth::RadikoAuthManager, program::RadikoProgram, station::RadikoStation,
+        auth::{AuthOptions, RadikoAuthManager},
+        broadcaster::StationBroadcaster,
+        endpoint::RadikoEndpoint,
+        http::RadikoHttpClient,
+        program::RadikoProgram,
+        session_store::SessionStore,
+        station::RadikoStation,
         stream::RadikoStream,
     },
+    error::Result,
     models::{
-        program::Programs, region::RegionStations, search::SearchCondition, station::Stations,
+        account::AccountInfo,
+        broadcast_day::BroadcastDay,
+        program::{Program, Programs},
+        region::RegionStations,
+        search::{SearchCondition, SearchResult},
+        station::Stations,
+        station_directory::StationDirectory,
     },
+    recorder::RadikoRecorder,
 };
-use anyhow::Result;
-use secrecy::{ExposeSecret, SecretString};
+use reqwest::{Certificate, Client, Proxy};
+use secrecy::SecretString;
 
+pub use crate::api::{
+    auth::{AuthStrategy, MobileAuth},
+    broadcaster::{StationEvent, StationSubscription},
+    endpoint::EndpointConfig,
+    http::RetryPolicy,
+    live_audio::{LiveAudio, LiveSegment},
+};
+
+const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36";
+
+/// 内部状態は共有されるので、クローンしたハンドルをシャットダウン処理などに渡せる
+#[derive(Clone)]
 pub struct Radiko {
-    inner: Arc<RwLock<RadikoRef>>,
+    inner: Arc<RadikoRef>,
 }
 
 struct RadikoRef {
+    http: RadikoHttpClient,
     auth_manager: Arc<RadikoAuthManager>,
     stream: RadikoStream,
+    broadcaster: StationBroadcaster,
     station: RadikoStation,
     program: RadikoProgram,
+}
+
+struct RadikoSettings {
+    http: RadikoHttpClient,
+    endpoint: RadikoEndpoint,
+    segment_retry: RetryPolicy,
+    reconnect: RetryPolicy,
     email: Option<SecretString>,
     password: Option<SecretString>,
+    strategy: AuthStrategy,
+    session_store: Option<SessionStore>,
 }
 
 impl Radiko {
-    pub async fn new() -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(Self::init_inner(None, None).await)),
-        }
+    pub async fn new() -> Result<Self> {
+        Self::builder().build().await
     }
 
-    pub async fn new_area_free(email: &str, password: &str) -> Self {
-        Self {
-            inner: Arc::new(RwLock::new(
-                Self::init_inner(
-                    Some(SecretString::new(email.into())),
-                    Some(SecretString::new(password.into())),
-                )
-                .await,
-            )),
-        }
+    pub async fn new_area_free(email: &str, password: &str) -> Result<Self> {
+        Self::builder().area_free(email, password).build().await
+    }
+
+    /// 接続先ホストを差し替えて初期化する
+    pub async fn new_with_endpoint_config(endpoint_config: EndpointConfig) -> Result<Self> {
+        Self::builder()
+            .endpoint_config(endpoint_config)
+            .build()
+            .await
+    }
+
+    pub async fn new_area_free_with_endpoint_config(
+        email: &str,
+        password: &str,
+        endpoint_config: EndpointConfig,
+    ) -> Result<Self> {
+        Self::builder()
+            .area_free(email, password)
+            .endpoint_config(endpoint_config)
+            .build()
+            .await
+    }
+
+    /// HTTPクライアントや接続先をカスタマイズして初期化する
+    pub fn builder() -> RadikoBuilder {
+        RadikoBuilder::default()
     }
 
-    async fn init_inner(email: Option<SecretString>, password: Option<SecretString>) -> RadikoRef {
-        let is_area_free = email.is_some() && password.is_some();
-        let shared_auth_manager = if is_area_free {
-            Arc::new(
-                RadikoAuthManager::new_area_free(
-                    email.clone().unwrap().expose_secret(),
-                    password.clone().unwrap().expose_secret(),
-                )
-                .await,
+    async fn init_inner(settings: RadikoSettings) -> Result<RadikoRef> {
+        let RadikoSettings {
+            http,
+            endpoint,
+            segment_retry,
+            reconnect,
+            email,
+            password,
+            strategy,
+            session_store,
+        } = settings;
+        let shared_auth_manager = Arc::new(
+            RadikoAuthManager::with_options(
+                http.clone(),
+                endpoint.clone(),
+                AuthOptions {
+                    mail: email,
+                    pass: password,
+                    strategy,
+                    session_store,
+                },
             )
-        } else {
-            Arc::new(RadikoAuthManager::new().await)
-        };
-        RadikoRef {
+            .await?,
+        );
+        let stream = RadikoStream::new(
+            Arc::clone(&shared_auth_manager),
+            http.clone(),
+            endpoint.clone(),
+            segment_retry,
+            reconnect,
+        );
+        Ok(RadikoRef {
+            http: http.clone(),
             auth_manager: Arc::clone(&shared_auth_manager),
-            stream: RadikoStream::new(Arc::clone(&shared_auth_manager)),
-            station: RadikoStation::new(),
-            program: RadikoProgram::new(),
-            email: email,
-            password: password,
-        }
+            broadcaster: StationBroadcaster::new(stream.clone()),
+            stream,
+            station: RadikoStation::new(http.clone(), endpoint.clone()),
+            program: RadikoProgram::new(http, endpoint),
+        })
     }
 
+    /// 認証トークンを取り直す
+    /// ストリームの取得では401/403を受けた時点で自動的に再認証されるので、通常は呼び出す必要はない
     pub async fn refresh_auth(&self) -> Result<()> {
-        let email = self.inner.read().await.email.clone();
-        let password = self.inner.read().await.password.clone();
-        let mut inner = self.inner.write().await;
-        *inner = Self::init_inner(email, password).await;
-        drop(inner);
-        Ok(())
+        self.inner.auth_manager.reauthenticate().await
+    }
+
+    /// エリアフリーのセッションをログアウトし、以降は非エリアフリーとして動作する
+    /// 保持しているcookie、ログイン情報、セッションファイルは破棄される
+    /// 非同期処理のため`Drop`では呼び出されない。ワーカーの終了時などに明示的に呼び出す
+    pub async fn logout(&self) -> Result<()> {
+        self.inner.auth_manager.logout().await
+    }
+
+    /// 認証トークンの発行からの経過時間
+    pub async fn token_age(&self) -> Duration {
+        self.inner.auth_manager.token_age()
+    }
+
+    /// 認証トークンが失効している可能性が高い場合にtrue
+    /// `stream_url`と`auth_token`を外部のプレイヤーに渡す前の確認に利用する
+    pub async fn is_probably_expired(&self) -> bool {
+        self.inner.auth_manager.is_probably_expired()
     }
 
     pub async fn area_id(&self) -> String {
-        self.inner.read().await.auth_manager.area_id().to_string()
+        self.inner.auth_manager.area_id()
     }
 
     pub async fn auth_token(&self) -> String {
-        self.inner
-            .read()
-            .await
-            .auth_manager
-            .auth_token()
-            .to_string()
+        self.inner.auth_manager.auth_token()
     }
 
-    pub async fn stream_url(&self, station_id: &str) -> String {
-        self.inner
-            .read()
-            .await
-            .stream
-            .stream_url(station_id)
-            .to_string()
+    /// エリアフリーでログインしたアカウントの情報
+    /// エリアフリーで初期化していない場合はNone
+    pub async fn account_info(&self) -> Option<AccountInfo> {
+        self.inner.auth_manager.account_info()
+    }
+
+    /// ライブ配信のMasterPlaylist.m3u8のURL
+    pub async fn stream_url(&self, station_id: &str) -> Result<String> {
+        self.inner.stream.stream_url(station_id).await
+    }
+
+    /// タイムフリーのMasterPlaylist.m3u8のURL
+    /// 放送前や放送開始から7日以上経過した番組は`RadikoError::TimefreeUnavailable`になる
+    pub async fn timefree_stream_url(&self, program: &Program) -> Result<String> {
+        self.inner.stream.timefree_stream_url(program).await
+    }
+
+    /// ライブ配信のAACを到着順に返すストリーム
+    /// ファイルを介さずに文字起こしや監視などの処理に渡す場合に使う
+    /// セグメントが欠落した場合は`LiveAudio::Gap`を返し、ストリームはその後も続く
+    pub async fn live_audio(
+        &self,
+        station_id: &str,
+    ) -> impl Stream<Item = Result<LiveAudio>> + Send + use<> {
+        self.inner.stream.live_audio(station_id)
+    }
+
+    /// ライブ配信を購読する
+    /// 同じ放送局の購読者は上流のHLSセッションを共有し、最後の購読者がdropした時点でセッションを終了する
+    /// 複数の録音や中継で同じ放送局を扱う場合に、radiko側への接続を1つにまとめられる
+    pub async fn subscribe(&self, station_id: &str) -> StationSubscription {
+        self.inner.broadcaster.subscribe(station_id)
+    }
+
+    /// 購読者がいて上流のセッションを持っている放送局
+    pub async fn broadcasting_stations(&self) -> Vec<String> {
+        self.inner.broadcaster.active_stations()
+    }
+
+    /// ffmpegを使わずにライブ配信の録音やタイムフリーのダウンロードを行う
+    pub async fn recorder(&self) -> RadikoRecorder {
+        let inner = &self.inner;
+        RadikoRecorder::new(
+            inner.stream.clone(),
+            inner.station.clone(),
+            inner.http.clone(),
+        )
+    }
+
+    /// 認証ヘッダーを付与できないプレイヤー向けのHLSリレーサーバー
+    #[cfg(feature = "relay")]
+    pub async fn relay(&self) -> crate::relay::RadikoRelay {
+        crate::relay::RadikoRelay::new(self.inner.stream.clone())
     }
 
     pub async fn stations_all(&self) -> Result<Vec<RegionStations>> {
-        self.inner.read().await.station.stations_all().await
+        self.inner.station.stations_all().await
+    }
+
+    /// 全国の放送局一覧の索引
+    /// 現在のエリアの放送局一覧も取り込むため、`availability(station_id, radiko.area_id().await)`で
+    /// 現在のエリアからライブで聴取できるか、エリアフリーでのみ聴取できるかを判定できる
+    pub async fn station_directory(&self) -> Result<StationDirectory> {
+        let inner = &self.inner;
+        let mut directory = StationDirectory::new(inner.station.stations_all().await?);
+        let area_id = inner.auth_manager.area_id();
+        directory.add_area_stations(&inner.station.stations_from_area_id(&area_id).await?);
+        Ok(directory)
     }
 
-    pub async fn stations_from_area_id(&self, area_id: &str) -> Result<Stations> {
+    /// area_idには`"JP13"`の他に`Area::Tokyo`も渡せる
+    pub async fn stations_from_area_id(&self, area_id: impl AsRef<str>) -> Result<Stations> {
         self.inner
-            .read()
-            .await
             .station
-            .stations_from_area_id(area_id)
+            .stations_from_area_id(area_id.as_ref())
             .await
     }
 
-    pub async fn now_on_air_programs(&self, area_id: &str) -> Result<Programs> {
+    /// area_idには`"JP13"`の他に`Area::Tokyo`も渡せる
+    pub async fn now_on_air_programs(&self, area_id: impl AsRef<str>) -> Result<Programs> {
         self.inner
-            .read()
-            .await
             .program
-            .now_on_air_programs(area_id)
+            .now_on_air_programs(area_id.as_ref())
             .await
     }
 
     pub async fn weekly_programs_from_station(&self, station_id: &str) -> Result<Programs> {
         self.inner
-            .read()
-            .await
             .program
             .weekly_programs_from_station(station_id)
             .await
     }
 
-    pub async fn find_program(&self, search_condition: &SearchCondition) -> Result<Programs> {
+    /// 放送日(5時〜翌5時)ごとのエリアの番組表
+    /// 深夜0時〜5時の番組は前日の放送日に含まれる
+    pub async fn programs_by_date(
+        &self,
+        area_id: impl AsRef<str>,
+        day: impl Into<BroadcastDay>,
+    ) -> Result<Programs> {
         self.inner
-            .read()
+            .program
+            .programs_by_date(area_id.as_ref(), day.into())
             .await
+    }
+
+    /// 放送日(5時〜翌5時)ごとの放送局の番組表
+    pub async fn programs_by_station_date(
+        &self,
+        station_id: &str,
+        day: impl Into<BroadcastDay>,
+    ) -> Result<Programs> {
+        self.inner
             .program
-            .find_program(search_condition)
+            .programs_by_station_date(station_id, day.into())
+            .await
+    }
+
+    /// 指定した日時に放送局で放送中の番組
+    /// 日時が属する放送日の番組表から探すため、`BroadcastDay::parse_hhmm("2500")`のような深夜の日時も指定できる
+    pub async fn program_at(
+        &self,
+        station_id: &str,
+        instant: DateTime<Tz>,
+    ) -> Result<Option<Program>> {
+        let programs = self
+            .programs_by_station_date(station_id, BroadcastDay::of(&instant))
+            .await?;
+        Ok(programs.at(station_id, &instant).cloned())
+    }
+
+    /// 今日の放送日のエリアの番組表
+    pub async fn today_programs(&self, area_id: impl AsRef<str>) -> Result<Programs> {
+        self.inner.program.today_programs(area_id.as_ref()).await
+    }
+
+    /// 検索条件の`page_idx`のページを検索する
+    pub async fn find_program(&self, search_condition: &SearchCondition) -> Result<SearchResult> {
+        self.inner.program.find_program(search_condition).await
+    }
+
+    /// 検索結果を最後のページまで1ページずつ返す
+    pub async fn search_pages(
+        &self,
+        search_condition: &SearchCondition,
+    ) -> impl Stream<Item = Result<SearchResult>> + Send + use<> {
+        self.inner.program.search_pages(search_condition)
+    }
+
+    /// 検索結果を全てのページから取得する
+    /// `row_limit`を超える件数がある場合も打ち切られない
+    pub async fn find_all_programs(&self, search_condition: &SearchCondition) -> Result<Programs> {
+        let pages: Vec<SearchResult> = self
+            .search_pages(search_condition)
             .await
+            .try_collect()
+            .await?;
+        Ok(Programs {
+            data: pages.into_iter().flat_map(|page| page.data).collect(),
+        })
+    }
+}
+
+/// `Radiko`の初期化設定
+/// `http_client`で構築済みの`reqwest::Client`を渡した場合、プロキシやタイムアウトなどのHTTP設定は無視される
+#[derive(Default)]
+pub struct RadikoBuilder {
+    client: Option<Client>,
+    user_agent: Option<String>,
+    proxies: Vec<Proxy>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    root_certificates: Vec<Certificate>,
+    danger_accept_invalid_certs: bool,
+    email: Option<SecretString>,
+    password: Option<SecretString>,
+    endpoint_config: EndpointConfig,
+    retry_policy: RetryPolicy,
+    segment_retry_policy: Option<RetryPolicy>,
+    reconnect_policy: Option<RetryPolicy>,
+    auth_strategy: AuthStrategy,
+    session_store_path: Option<PathBuf>,
+}
+
+impl RadikoBuilder {
+    /// 構築済みのHTTPクライアントを全APIで共有する
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// 社内プロキシなどで独自のルート証明書が必要な場合に追加する
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.danger_accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// エリアフリー(プレミアム会員)としてログインする
+    pub fn area_free(mut self, email: &str, password: &str) -> Self {
+        self.email = Some(SecretString::new(email.into()));
+        self.password = Some(SecretString::new(password.into()));
+        self
+    }
+
+    pub fn endpoint_config(mut self, endpoint_config: EndpointConfig) -> Self {
+        self.endpoint_config = endpoint_config;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// ライブ配信のセグメントのダウンロードを再試行する方針
+    /// 既定は`RetryPolicy::segment()`
+    pub fn segment_retry_policy(mut self, segment_retry_policy: RetryPolicy) -> Self {
+        self.segment_retry_policy = Some(segment_retry_policy);
+        self
+    }
+
+    /// ライブ配信のプレイリストの取得に失敗した場合に再接続する方針
+    /// `max_retries`回続けて失敗すると録音やストリームをエラーで終了する。既定は`RetryPolicy::reconnect()`
+    pub fn reconnect_policy(mut self, reconnect_policy: RetryPolicy) -> Self {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+
+    /// 認証方式
+    /// `AuthStrategy::Mobile`を指定すると接続元のIPアドレスに関わらず指定したエリアとして認証する
+    pub fn auth_strategy(mut self, auth_strategy: AuthStrategy) -> Self {
+        self.auth_strategy = auth_strategy;
+        self
+    }
+
+    /// 認証結果を保存するファイル
+    /// 指定すると次回以降の初期化でログインや認証キーの取得を省略し、期限内であれば認証トークンも再利用する
+    /// ファイルが壊れている場合やアカウントが異なる場合は通常どおり認証して上書きする
+    pub fn session_store_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.session_store_path = Some(path.into());
+        self
+    }
+
+    pub async fn build(self) -> Result<Radiko> {
+        let settings = RadikoSettings {
+            http: RadikoHttpClient::new(self.build_client()?, self.retry_policy),
+            endpoint: RadikoEndpoint::new(self.endpoint_config),
+            segment_retry: self
+                .segment_retry_policy
+                .unwrap_or_else(RetryPolicy::segment),
+            reconnect: self.reconnect_policy.unwrap_or_else(RetryPolicy::reconnect),
+            email: self.email,
+            password: self.password,
+            strategy: self.auth_strategy,
+            session_store: self.session_store_path.map(SessionStore::new),
+        };
+        Ok(Radiko {
+            inner: Arc::new(Radiko::init_inner(settings).await?),
+        })
+    }
+
+    fn build_client(&self) -> Result<Client> {
+        if let Some(client) = &self.client {
+            return Ok(client.clone());
+        }
+
+        let mut builder = Client::builder()
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .danger_accept_invalid_certs(self.danger_accept_invalid_certs);
+        for proxy in &self.proxies {
+            builder = builder.proxy(proxy.clone());
+        }
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{MockResponse, MockServer, mock_auth_routes, mock_login_routes};
+
+    #[tokio::test]
+    async fn builder_shares_http_settings_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server.route(
+            "/v3/station/list/JP13.xml",
+            MockResponse::ok(include_str!("../examples/radiko/JP13.xml")),
+        );
+
+        let radiko = Radiko::builder()
+            .user_agent("radiko-rs-test")
+            .timeout(Duration::from_secs(5))
+            .retry_policy(RetryPolicy::none())
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .build()
+            .await?;
+        radiko.stations_from_area_id("JP13").await?;
+
+        let requests = server.requests();
+        assert!(!requests.is_empty());
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.header("user-agent") == Some("radiko-rs-test"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn logout_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        mock_login_routes(&server);
+        server.route("/v4/api/member/logout", MockResponse::ok(""));
+        let dir = tempfile::tempdir()?;
+
+        let radiko = Radiko::builder()
+            .area_free("mail@example.com", "pass")
+            .session_store_path(dir.path().join("session.json"))
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .build()
+            .await?;
+        assert!(radiko.account_info().await.is_some());
+
+        radiko.clone().logout().await?;
+
+        let logout_requests = server.requests_to("/v4/api/member/logout");
+        assert_eq!(logout_requests.len(), 1);
+        assert_eq!(logout_requests[0].method, "POST");
+        assert_eq!(
+            logout_requests[0].header("cookie"),
+            Some("radiko_session=mock_session")
+        );
+        assert!(radiko.account_info().await.is_none());
+        let last_auth1 = server.requests_to("/v2/api/auth1").pop().unwrap();
+        assert_eq!(last_auth1.header("cookie"), None);
+
+        // ログアウト後は新しいセッションファイルにログイン情報を残さない
+        let session = std::fs::read_to_string(dir.path().join("session.json"))?;
+        assert!(!session.contains("mock_session"));
+        Ok(())
     }
 }
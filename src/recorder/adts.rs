@@ -0,0 +1,205 @@
+//! radikoのセグメントはADTS形式のAACの先頭にタイムスタンプ用のID3タグが付与されている
+//! 連結して1つの`.aac`ファイルにするためにID3タグを取り除く
+//! MP4コンテナに格納する場合はADTSヘッダーも取り除き、AACのフレーム単位に分割する
+
+use crate::error::{RadikoError, Result};
+
+const ID3_HEADER_LEN: usize = 10;
+const ID3_FOOTER_FLAG: u8 = 0x10;
+
+const ADTS_HEADER_LEN: usize = 7;
+const ADTS_CRC_LEN: usize = 2;
+/// AACの1フレームあたりのサンプル数
+pub const SAMPLES_PER_FRAME: u32 = 1024;
+const SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// ADTSヘッダーから取得したAACの設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioConfig {
+    /// MPEG-4 Audio Object Type (AAC LCは2)
+    pub object_type: u8,
+    pub sampling_frequency_index: u8,
+    pub channel_config: u8,
+}
+
+impl AudioConfig {
+    pub fn sample_rate(&self) -> u32 {
+        SAMPLE_RATES[self.sampling_frequency_index as usize]
+    }
+
+    /// MP4のesdsに格納するAudioSpecificConfig
+    pub fn audio_specific_config(&self) -> [u8; 2] {
+        [
+            (self.object_type << 3) | (self.sampling_frequency_index >> 1),
+            ((self.sampling_frequency_index & 1) << 7) | (self.channel_config << 3),
+        ]
+    }
+}
+
+/// ADTSヘッダーを取り除いたAACのフレーム
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdtsFrame {
+    pub config: AudioConfig,
+    pub payload: Vec<u8>,
+}
+
+/// セグメント単位で届くADTSストリームをフレームに分割する
+/// セグメントの境界で分割されたフレームは次のセグメントと連結してから取り出す
+#[derive(Debug, Default)]
+pub struct AdtsReader {
+    buffer: Vec<u8>,
+}
+
+impl AdtsReader {
+    /// データを追加し、取り出せるようになったフレームを返す
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<AdtsFrame>> {
+        self.buffer.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+        let mut position = 0;
+        while self.buffer.len() - position >= ADTS_HEADER_LEN {
+            let header = &self.buffer[position..];
+            // 同期ワードが見つかるまで読み飛ばす
+            if header[0] != 0xff || header[1] & 0xf6 != 0xf0 {
+                position += 1;
+                continue;
+            }
+            let protection_absent = header[1] & 0x01 != 0;
+            let sampling_frequency_index = (header[2] >> 2) & 0x0f;
+            let frame_len = (((header[3] & 0x03) as usize) << 11)
+                | ((header[4] as usize) << 3)
+                | ((header[5] as usize) >> 5);
+            let header_len = if protection_absent {
+                ADTS_HEADER_LEN
+            } else {
+                ADTS_HEADER_LEN + ADTS_CRC_LEN
+            };
+            if sampling_frequency_index as usize >= SAMPLE_RATES.len() || frame_len <= header_len {
+                position += 1;
+                continue;
+            }
+            if header[6] & 0x03 != 0 {
+                return Err(RadikoError::InvalidAudio(
+                    "multiple raw data blocks in an ADTS frame are not supported".to_string(),
+                ));
+            }
+            if header.len() < frame_len {
+                break;
+            }
+
+            frames.push(AdtsFrame {
+                config: AudioConfig {
+                    object_type: (header[2] >> 6) + 1,
+                    sampling_frequency_index,
+                    channel_config: ((header[2] & 0x01) << 2) | (header[3] >> 6),
+                },
+                payload: header[header_len..frame_len].to_vec(),
+            });
+            position += frame_len;
+        }
+        self.buffer.drain(..position);
+
+        Ok(frames)
+    }
+}
+
+/// 先頭に連続するID3v2タグを取り除いたADTSフレームを返す
+pub fn strip_id3(segment: &[u8]) -> &[u8] {
+    let mut data = segment;
+    while let Some(tag_len) = id3_tag_len(data) {
+        data = data.get(tag_len..).unwrap_or_default();
+    }
+    data
+}
+
+/// ID3v2タグのヘッダー、本体、フッターを合わせた長さ
+fn id3_tag_len(data: &[u8]) -> Option<usize> {
+    if data.len() < ID3_HEADER_LEN || !data.starts_with(b"ID3") {
+        return None;
+    }
+    // サイズは各バイトの下位7bitを使うsyncsafe integer
+    let size = data[6..10]
+        .iter()
+        .fold(0usize, |size, byte| (size << 7) | (*byte & 0x7f) as usize);
+    let footer_len = if data[5] & ID3_FOOTER_FLAG != 0 {
+        ID3_HEADER_LEN
+    } else {
+        0
+    };
+    Some(ID3_HEADER_LEN + size + footer_len)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn id3_tag(payload: &[u8]) -> Vec<u8> {
+        let size = payload.len();
+        let mut tag = b"ID3\x04\x00\x00".to_vec();
+        tag.extend([
+            ((size >> 21) & 0x7f) as u8,
+            ((size >> 14) & 0x7f) as u8,
+            ((size >> 7) & 0x7f) as u8,
+            (size & 0x7f) as u8,
+        ]);
+        tag.extend_from_slice(payload);
+        tag
+    }
+
+    /// AAC LC、24kHz、ステレオのADTSフレーム
+    pub(crate) fn adts_frame(payload: &[u8]) -> Vec<u8> {
+        let frame_len = ADTS_HEADER_LEN + payload.len();
+        let mut frame = vec![
+            0xff,
+            0xf1,
+            0x58,
+            0x80 | ((frame_len >> 11) & 0x03) as u8,
+            ((frame_len >> 3) & 0xff) as u8,
+            (((frame_len & 0x07) << 5) as u8) | 0x1f,
+            0xfc,
+        ];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn strip_id3_test() {
+        let adts = [0xff, 0xf1, 0x50, 0x80, 0x01, 0xff, 0xfc];
+        let mut segment = id3_tag(&[0u8; 200]);
+        segment.extend(id3_tag(b"PRIV"));
+        segment.extend_from_slice(&adts);
+
+        assert_eq!(strip_id3(&segment), adts);
+        assert_eq!(strip_id3(&adts), adts);
+        assert_eq!(strip_id3(&id3_tag(b"only tag")), b"");
+        // 壊れたタグはそれ以上読み進めない
+        assert_eq!(strip_id3(b"ID3\x04\x00\x00\x7f\x7f\x7f\x7f"), b"");
+    }
+
+    #[test]
+    fn adts_reader_test() -> anyhow::Result<()> {
+        let mut stream = vec![0x00, 0x12];
+        stream.extend(adts_frame(b"first"));
+        stream.extend(adts_frame(b"second"));
+        let (head, tail) = stream.split_at(18);
+        let mut reader = AdtsReader::default();
+
+        let frames = reader.push(head)?;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload, b"first");
+        let config = frames[0].config;
+        assert_eq!(config.object_type, 2);
+        assert_eq!(config.sample_rate(), 24000);
+        assert_eq!(config.channel_config, 2);
+        assert_eq!(config.audio_specific_config(), [0x13, 0x10]);
+
+        // セグメントの境界で分割されたフレームは連結される
+        let frames = reader.push(tail)?;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload, b"second");
+        assert!(reader.push(&[])?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,477 @@
+//! 番組と放送局の情報から録音ファイルのパスを作成するテンプレート
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{
+    DateTime,
+    format::{Item, StrftimeItems},
+};
+use chrono_tz::Tz;
+
+use crate::{
+    error::{RadikoError, Result},
+    models::{program::Program, station::Station},
+};
+
+/// 日時の書式を省略した場合の書式
+const DEFAULT_DATETIME_FORMAT: &str = "%Y%m%d%H%M";
+/// 多くのファイルシステムでのファイル名の上限(byte)
+const DEFAULT_MAX_LENGTH: usize = 255;
+/// 拡張子として扱う最大の長さ
+const MAX_EXTENSION_LENGTH: usize = 16;
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 出力先に同名のファイルが既にある場合の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// 拡張子の前に`_2`、`_3`...を付けて重複しない名前にする
+    #[default]
+    Rename,
+    Overwrite,
+    /// `std::io::ErrorKind::AlreadyExists`のエラーにする
+    Error,
+}
+
+/// 録音ファイルのパスのテンプレート
+///
+/// `{station_id}/{start_time:%Y%m%d}_{title}.m4a`のように`{フィールド名}`を番組と放送局の値に置き換える
+/// - `station_id`, `station_name`, `station_ascii_name`, `title`, `performer`
+/// - `start_time`, `end_time`: `:`の後にchronoの書式を指定できる。省略時は`%Y%m%d%H%M`
+///
+/// 置き換えた値に含まれる`/`やファイル名に使えない文字は置換されるので、ディレクトリはテンプレートの`/`でのみ区切られる
+/// `{`と`}`そのものは`{{`と`}}`で書く
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate {
+    parts: Vec<TemplatePart>,
+    max_length: usize,
+    collision_policy: CollisionPolicy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Literal(String),
+    Field(Field),
+    DateTime(DateTimeField, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    StationId,
+    StationName,
+    StationAsciiName,
+    Title,
+    Performer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateTimeField {
+    StartTime,
+    EndTime,
+}
+
+impl FilenameTemplate {
+    pub fn new(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| {
+                        RadikoError::InvalidArgument(format!(
+                            "unclosed placeholder in filename template: {}",
+                            template
+                        ))
+                    })?;
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Self::parse_placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    return Err(RadikoError::InvalidArgument(format!(
+                        "unmatched '}}' in filename template: {}",
+                        template
+                    )));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+
+        Ok(Self {
+            parts,
+            max_length: DEFAULT_MAX_LENGTH,
+            collision_policy: CollisionPolicy::default(),
+        })
+    }
+
+    fn parse_placeholder(placeholder: &str) -> Result<TemplatePart> {
+        let (name, format) = match placeholder.split_once(':') {
+            Some((name, format)) => (name, Some(format)),
+            None => (placeholder, None),
+        };
+        let datetime_field = match name {
+            "start_time" => Some(DateTimeField::StartTime),
+            "end_time" => Some(DateTimeField::EndTime),
+            _ => None,
+        };
+        if let Some(datetime_field) = datetime_field {
+            let format = format.unwrap_or(DEFAULT_DATETIME_FORMAT);
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(RadikoError::InvalidArgument(format!(
+                    "invalid datetime format in filename template: {}",
+                    format
+                )));
+            }
+            return Ok(TemplatePart::DateTime(datetime_field, format.to_string()));
+        }
+        if format.is_some() {
+            return Err(RadikoError::InvalidArgument(format!(
+                "format is only supported for start_time and end_time: {}",
+                placeholder
+            )));
+        }
+
+        let field = match name {
+            "station_id" => Field::StationId,
+            "station_name" => Field::StationName,
+            "station_ascii_name" => Field::StationAsciiName,
+            "title" => Field::Title,
+            "performer" => Field::Performer,
+            _ => {
+                return Err(RadikoError::InvalidArgument(format!(
+                    "unknown field in filename template: {}",
+                    name
+                )));
+            }
+        };
+        Ok(TemplatePart::Field(field))
+    }
+
+    /// ディレクトリ名、ファイル名それぞれの最大の長さ(byte)
+    /// 超える場合は拡張子を残して末尾を切り詰める
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    pub fn with_collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.collision_policy = collision_policy;
+        self
+    }
+
+    /// テンプレートを展開した相対パス
+    /// 放送局の情報が無い場合、放送局名は放送局IDで代替する
+    pub fn render(&self, program: &Program, station: Option<&Station>) -> PathBuf {
+        let rendered = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(literal) => literal.clone(),
+                TemplatePart::Field(field) => sanitize(&field.value(program, station)),
+                TemplatePart::DateTime(field, format) => {
+                    sanitize(&field.value(program).format(format).to_string())
+                }
+            })
+            .collect::<String>();
+
+        let components = rendered
+            .split('/')
+            .filter(|component| !component.is_empty())
+            .collect::<Vec<_>>();
+        let last_index = components.len().saturating_sub(1);
+        components
+            .iter()
+            .enumerate()
+            .map(|(index, component)| {
+                if index == last_index {
+                    let (stem, extension) = split_extension(component);
+                    fit_file_name(stem, extension, "", self.max_length)
+                } else {
+                    fit_file_name(component, "", "", self.max_length)
+                }
+            })
+            .collect()
+    }
+
+    /// base_dirを起点にしたパスを返す
+    /// 同名のファイルがある場合は`CollisionPolicy`に従う。ディレクトリは作成しない
+    pub fn resolve(
+        &self,
+        base_dir: impl AsRef<Path>,
+        program: &Program,
+        station: Option<&Station>,
+    ) -> Result<PathBuf> {
+        let path = base_dir.as_ref().join(self.render(program, station));
+        if !path.exists() {
+            return Ok(path);
+        }
+
+        match self.collision_policy {
+            CollisionPolicy::Overwrite => Ok(path),
+            CollisionPolicy::Error => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("recording already exists: {}", path.display()),
+            )
+            .into()),
+            CollisionPolicy::Rename => {
+                let file_name = path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let (stem, extension) = split_extension(&file_name);
+                (2..)
+                    .map(|number| {
+                        path.with_file_name(fit_file_name(
+                            stem,
+                            extension,
+                            &format!("_{}", number),
+                            self.max_length,
+                        ))
+                    })
+                    .find(|candidate| !candidate.exists())
+                    .ok_or_else(|| {
+                        RadikoError::InvalidArgument(format!(
+                            "no available file name for {}",
+                            path.display()
+                        ))
+                    })
+            }
+        }
+    }
+}
+
+impl FromStr for FilenameTemplate {
+    type Err = RadikoError;
+
+    fn from_str(template: &str) -> Result<Self> {
+        Self::new(template)
+    }
+}
+
+impl Field {
+    fn value(&self, program: &Program, station: Option<&Station>) -> String {
+        match self {
+            Self::StationId => program.station_id.clone(),
+            Self::StationName => station
+                .map_or(&program.station_id, |station| &station.name)
+                .clone(),
+            Self::StationAsciiName => station
+                .map_or(&program.station_id, |station| &station.ascii_name)
+                .clone(),
+            Self::Title => program.title.clone(),
+            Self::Performer => program.performer.clone(),
+        }
+    }
+}
+
+impl DateTimeField {
+    fn value(&self, program: &Program) -> DateTime<Tz> {
+        match self {
+            Self::StartTime => program.start_time,
+            Self::EndTime => program.end_time,
+        }
+    }
+}
+
+/// ファイル名に使える文字列にする
+/// 全角英数記号と全角空白は半角にした上で、パス区切りやWindowsで使えない記号を`_`に置換する
+fn sanitize(value: &str) -> String {
+    let replaced = value
+        .chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            c => c,
+        })
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect::<String>();
+    let collapsed = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = collapsed.trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return "_".to_string();
+    }
+    let base_name = trimmed.split('.').next().unwrap_or_default();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base_name))
+    {
+        return format!("_{}", trimmed);
+    }
+    trimmed.to_string()
+}
+
+/// 拡張子を含めた長さがmax_lengthに収まるようにstemを切り詰め、suffixと拡張子を付ける
+fn fit_file_name(stem: &str, extension: &str, suffix: &str, max_length: usize) -> String {
+    let extension = if extension.is_empty() {
+        String::new()
+    } else {
+        format!(".{}", extension)
+    };
+    let available = max_length.saturating_sub(suffix.len() + extension.len());
+    let mut end = stem.len().min(available);
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    let stem = stem[..end].trim_end_matches(['.', ' ']);
+    format!("{}{}{}", stem, suffix, extension)
+}
+
+fn split_extension(file_name: &str) -> (&str, &str) {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension))
+            if !stem.is_empty()
+                && !extension.is_empty()
+                && extension.len() <= MAX_EXTENSION_LENGTH =>
+        {
+            (stem, extension)
+        }
+        _ => (file_name, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::Asia::Tokyo;
+
+    fn program(title: &str) -> Program {
+        let start_time = Tokyo.with_ymd_and_hms(2025, 7, 1, 1, 0, 0).unwrap();
+        Program {
+            performer: "出演者".to_string(),
+            title: title.to_string(),
+            ..Program::fixture(start_time, start_time + chrono::Duration::hours(2))
+        }
+    }
+
+    fn station() -> Station {
+        Station {
+            id: "TBS".to_string(),
+            name: "TBSラジオ".to_string(),
+            ascii_name: "TBS RADIO".to_string(),
+            ruby: "".to_string(),
+            areafree: true,
+            timefree: true,
+            logos: Vec::new(),
+            banner: "".to_string(),
+            href: "".to_string(),
+            simul_max_delay: 15,
+            tf_max_delay: 90,
+        }
+    }
+
+    #[test]
+    fn render_test() -> anyhow::Result<()> {
+        let template: FilenameTemplate =
+            "{station_name}/{start_time:%Y%m%d}_{title}_{{{performer}}}.m4a".parse()?;
+
+        let path = template.render(
+            &program("ＪＵＮＫ　爆笑問題カーボーイ／特番: 1/2 <生放送>?"),
+            Some(&station()),
+        );
+
+        assert_eq!(
+            path,
+            Path::new(
+                "TBSラジオ/20250701_JUNK 爆笑問題カーボーイ_特番_ 1_2 _生放送___{出演者}.m4a"
+            )
+        );
+        assert_eq!(
+            FilenameTemplate::new("{station_name}_{end_time}.aac")?.render(&program("t"), None),
+            Path::new("TBS_202507010300.aac")
+        );
+        assert_eq!(
+            FilenameTemplate::new("{title}.aac")?.render(&program(" .. "), None),
+            Path::new("_.aac")
+        );
+        assert_eq!(
+            FilenameTemplate::new("{title}.aac")?.render(&program("con"), None),
+            Path::new("_con.aac")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_template_test() {
+        for template in [
+            "{title",
+            "title}",
+            "{unknown}",
+            "{title:%Y}",
+            "{start_time:%Q}",
+        ] {
+            assert!(
+                matches!(
+                    FilenameTemplate::new(template),
+                    Err(RadikoError::InvalidArgument(_))
+                ),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn max_length_test() -> anyhow::Result<()> {
+        let template = FilenameTemplate::new("{title}/{title}.m4a")?.with_max_length(20);
+
+        let path = template.render(&program("あいうえおかきくけこ"), None);
+
+        // マルチバイト文字の途中で切らずに拡張子を残す
+        assert_eq!(path, Path::new("あいうえおか/あいうえお.m4a"));
+        Ok(())
+    }
+
+    #[test]
+    fn collision_test() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let template = FilenameTemplate::new("{title}.m4a")?.with_max_length(13);
+        let program = program("recording");
+        std::fs::write(dir.path().join("recording.m4a"), b"")?;
+        std::fs::write(dir.path().join("recordi_2.m4a"), b"")?;
+
+        assert_eq!(
+            template.resolve(dir.path(), &program, None)?,
+            dir.path().join("recordi_3.m4a")
+        );
+        assert_eq!(
+            template
+                .clone()
+                .with_collision_policy(CollisionPolicy::Overwrite)
+                .resolve(dir.path(), &program, None)?,
+            dir.path().join("recording.m4a")
+        );
+        let result = template
+            .with_collision_policy(CollisionPolicy::Error)
+            .resolve(dir.path(), &program, None);
+        assert!(
+            matches!(result, Err(RadikoError::Io(err)) if err.kind() == io::ErrorKind::AlreadyExists)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+//! `.aac`ファイルの先頭に書き込むID3v2.4タグ
+
+use super::metadata::RecordingMetadata;
+
+/// テキストフレームのエンコーディング(UTF-8)
+const ENCODING_UTF8: u8 = 0x03;
+/// APICの画像の種類(表紙)
+const PICTURE_TYPE_FRONT_COVER: u8 = 0x03;
+const COMMENT_LANGUAGE: &[u8; 3] = b"jpn";
+
+/// タグを作成する。書き込む項目が無い場合は空
+pub fn tag(metadata: &RecordingMetadata) -> Vec<u8> {
+    let mut frames = Vec::new();
+    for (id, text) in [
+        (b"TIT2", &metadata.title),
+        (b"TPE1", &metadata.artist),
+        (b"TALB", &metadata.album),
+        (b"TDRC", &metadata.date),
+    ] {
+        if !text.is_empty() {
+            frames.extend(frame(id, &[&[ENCODING_UTF8][..], text.as_bytes()].concat()));
+        }
+    }
+    if !metadata.comment.is_empty() {
+        frames.extend(frame(
+            b"COMM",
+            &[
+                &[ENCODING_UTF8][..],
+                COMMENT_LANGUAGE,
+                // 短い説明は空
+                &[0x00],
+                metadata.comment.as_bytes(),
+            ]
+            .concat(),
+        ));
+    }
+    if let Some(cover) = &metadata.cover {
+        frames.extend(frame(
+            b"APIC",
+            &[
+                &[ENCODING_UTF8][..],
+                cover.format.mime_type().as_bytes(),
+                &[0x00, PICTURE_TYPE_FRONT_COVER, 0x00],
+                &cover.data,
+            ]
+            .concat(),
+        ));
+    }
+    if frames.is_empty() {
+        return frames;
+    }
+
+    [&b"ID3\x04\x00\x00"[..], &syncsafe(frames.len()), &frames].concat()
+}
+
+fn frame(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    [&id[..], &syncsafe(payload.len()), &[0x00, 0x00], payload].concat()
+}
+
+/// 各バイトの下位7bitを使うsyncsafe integer
+fn syncsafe(size: usize) -> [u8; 4] {
+    [
+        ((size >> 21) & 0x7f) as u8,
+        ((size >> 14) & 0x7f) as u8,
+        ((size >> 7) & 0x7f) as u8,
+        (size & 0x7f) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::{
+        adts::strip_id3,
+        metadata::{CoverArt, ImageFormat},
+    };
+
+    #[test]
+    fn id3_tag_test() {
+        let metadata = RecordingMetadata {
+            title: "番組".to_string(),
+            artist: "出演者".to_string(),
+            album: "TBSラジオ".to_string(),
+            date: "2025-07-01T01:00:00".to_string(),
+            comment: "説明".to_string(),
+            cover: Some(CoverArt {
+                format: ImageFormat::Png,
+                data: vec![0u8; 200],
+            }),
+        };
+
+        let tag = tag(&metadata);
+
+        assert!(tag.starts_with(b"ID3\x04\x00\x00"));
+        // ID3タグとして読み飛ばせる長さになっている
+        assert!(strip_id3(&tag).is_empty());
+        let title = [&b"TIT2\x00\x00\x00\x07\x00\x00\x03"[..], "番組".as_bytes()].concat();
+        assert!(tag.windows(title.len()).any(|window| window == title));
+        assert!(tag.windows(10).any(|window| window == b"image/png\0"));
+        assert!(super::tag(&RecordingMetadata::default()).is_empty());
+    }
+}
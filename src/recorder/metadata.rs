@@ -0,0 +1,156 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::models::program::Program;
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+static LINE_BREAK_TAG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<br\s*/?>|</p\s*>|</div\s*>|</li\s*>").expect("valid line break tag pattern")
+});
+static HTML_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[^>]*>").expect("valid html tag pattern"));
+static HTML_ENTITY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").expect("valid html entity pattern")
+});
+
+/// 録音ファイルに埋め込むタグ
+/// `.aac`ではID3v2、`.m4a`ではMP4のilstとして書き込む
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordingMetadata {
+    pub title: String,
+    /// 出演者
+    pub artist: String,
+    /// 放送局名
+    pub album: String,
+    /// 放送開始日時(`%Y-%m-%dT%H:%M:%S`)
+    pub date: String,
+    /// HTMLタグを取り除いた番組の説明
+    pub comment: String,
+    pub cover: Option<CoverArt>,
+}
+
+impl RecordingMetadata {
+    /// カバーアートを除いた番組情報からタグを作成する
+    pub fn from_program(program: &Program, station_name: &str) -> Self {
+        let description = if program.description.trim().is_empty() {
+            &program.info
+        } else {
+            &program.description
+        };
+        Self {
+            title: program.title.clone(),
+            artist: program.performer.clone(),
+            album: station_name.to_string(),
+            date: program.start_time.format(DATE_FORMAT).to_string(),
+            comment: strip_html(description),
+            cover: None,
+        }
+    }
+}
+
+/// カバーアートの画像
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverArt {
+    pub format: ImageFormat,
+    pub data: Vec<u8>,
+}
+
+impl CoverArt {
+    /// 埋め込みに対応していない形式の場合はNone
+    pub fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let format = ImageFormat::detect(&data)?;
+        Some(Self { format, data })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+}
+
+impl ImageFormat {
+    /// 先頭のシグネチャから画像の形式を判定する
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(Self::Jpeg)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+        }
+    }
+}
+
+/// 番組の説明に含まれるHTMLをプレーンテキストにする
+/// 改行に相当するタグは改行にし、空行と各行の前後の空白は取り除く
+fn strip_html(html: &str) -> String {
+    let text = LINE_BREAK_TAG.replace_all(html, "\n");
+    let text = HTML_TAG.replace_all(&text, "");
+    let text = HTML_ENTITY.replace_all(&text, |captures: &regex::Captures| {
+        decode_entity(&captures[1]).unwrap_or_else(|| captures[0].to_string())
+    });
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn decode_entity(entity: &str) -> Option<String> {
+    let decoded = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => entity.strip_prefix('#')?.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some(decoded.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_test() {
+        let description = r#"<p>番組の説明です。<br />
+  メールは<a href="mailto:test@example.com">こちら</a>&amp;&#x3042;&#12354;&hellip;</p>
+<p><img src="https://example.com/a.png"></p><p>最終行</p>"#;
+
+        assert_eq!(
+            strip_html(description),
+            "番組の説明です。\nメールはこちら&ああ&hellip;\n最終行"
+        );
+    }
+
+    #[test]
+    fn detect_image_format_test() {
+        assert_eq!(
+            ImageFormat::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            CoverArt::from_bytes(b"\x89PNG\r\n\x1a\n....".to_vec()).map(|cover| cover.format),
+            Some(ImageFormat::Png)
+        );
+        assert!(CoverArt::from_bytes(b"GIF89a".to_vec()).is_none());
+    }
+}
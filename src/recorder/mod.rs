@@ -0,0 +1,687 @@
+//! ffmpegを使わずにライブ配信の録音とタイムフリーのダウンロードを行う
+//! メディアプレイリストからAACのセグメントを取得し、連続したADTS形式の`.aac`ファイルまたはMP4の`.m4a`ファイルに書き出す
+
+pub(crate) mod adts;
+mod filename;
+mod id3;
+mod metadata;
+mod mp4;
+mod report;
+mod writer;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::{Asia::Tokyo, Tz};
+
+use crate::{
+    api::{http::RadikoHttpClient, station::RadikoStation, stream::RadikoStream},
+    error::{RadikoError, Result},
+    models::{program::Program, station::Station, station_directory::StationDirectory},
+    utils::ensure_success,
+};
+
+pub use filename::{CollisionPolicy, FilenameTemplate};
+pub use metadata::{CoverArt, ImageFormat, RecordingMetadata};
+pub use report::{RecordingGap, RecordingReport};
+use writer::AudioWriter;
+
+/// 録音ファイルの形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// ADTS形式のAACを連結した`.aac`
+    #[default]
+    Adts,
+    /// AACをMP4コンテナに格納した`.m4a`
+    /// 再生時間とシーク位置の情報を持つため、音楽アプリやスマートフォンでそのまま再生できる
+    M4a,
+}
+
+/// ライブ配信の録音を終了する条件
+#[derive(Debug, Clone, Copy)]
+enum StopAt {
+    /// 録音した音声の長さ
+    Elapsed(Duration),
+    /// セグメントの放送上の開始日時
+    /// プレイリストは実際の放送より遅れるので、現在時刻ではなく取得した音声の日時で判定する
+    EndTime(DateTime<Tz>),
+}
+
+/// ライブ配信の録音とタイムフリーのダウンロード
+/// `Radiko::recorder`で取得する
+#[derive(Clone)]
+pub struct RadikoRecorder {
+    stream: RadikoStream,
+    station: RadikoStation,
+    http: RadikoHttpClient,
+    output_format: OutputFormat,
+}
+
+impl RadikoRecorder {
+    pub(crate) fn new(
+        stream: RadikoStream,
+        station: RadikoStation,
+        http: RadikoHttpClient,
+    ) -> Self {
+        Self {
+            stream,
+            station,
+            http,
+            output_format: OutputFormat::default(),
+        }
+    }
+
+    /// 録音ファイルの形式を指定する
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// 指定した長さの音声を録音し、録音結果を返す
+    /// 録音は開始時点のプレイリストに含まれるセグメントから始まるため、数十秒前の音声から記録される
+    /// 再試行しても取得できなかったセグメントは飛ばして録音を続け、`RecordingReport::gaps`に記録する
+    pub async fn record(
+        &self,
+        station_id: &str,
+        duration: Duration,
+        path: impl AsRef<Path>,
+    ) -> Result<RecordingReport> {
+        let report = RecordingReport::new(station_id, Utc::now().with_timezone(&Tokyo), duration);
+        self.record_with_metadata(report, StopAt::Elapsed(duration), path, None)
+            .await
+    }
+
+    async fn record_with_metadata(
+        &self,
+        mut report: RecordingReport,
+        stop_at: StopAt,
+        path: impl AsRef<Path>,
+        metadata: Option<RecordingMetadata>,
+    ) -> Result<RecordingReport> {
+        if report.station_id.is_empty() {
+            return Err(RadikoError::InvalidArgument(
+                "station_id required.".to_string(),
+            ));
+        }
+
+        let mut session = self.stream.live_session(&report.station_id).await?;
+        let mut writer = AudioWriter::create(self.output_format, path, metadata).await?;
+        let mut last_sequence = None;
+        loop {
+            // 欠落した区間も時間は経過しているので、録音の終了判定に含める
+            if let StopAt::Elapsed(duration) = stop_at
+                && report.elapsed() >= duration
+            {
+                break;
+            }
+            let segment = match session.next_segment().await {
+                Ok(Some(segment)) => segment,
+                Ok(None) => break,
+                Err(err) => {
+                    report.add_session_stats(session.stats());
+                    // 書き出せたところまでは残すが、失敗の原因は元のエラーとして返す
+                    let _ = writer.finish().await;
+                    return Err(err);
+                }
+            };
+            if let StopAt::EndTime(end_time) = stop_at
+                && segment
+                    .start_time
+                    .is_some_and(|start_time| start_time >= end_time)
+            {
+                break;
+            }
+
+            // 再接続の間にプレイリストから消えたセグメント
+            if let Some(last_sequence) = last_sequence
+                && segment.sequence > last_sequence + 1
+            {
+                let missing_segments = segment.sequence - last_sequence - 1;
+                report.push_gap(
+                    last_sequence + 1,
+                    segment.sequence - 1,
+                    segment.duration * missing_segments as u32,
+                );
+            }
+            last_sequence = Some(segment.sequence);
+            match session.download(&segment).await {
+                Ok(data) => {
+                    writer.write_segment(&data).await?;
+                    report.recorded += segment.duration;
+                }
+                Err(_) => report.push_gap(segment.sequence, segment.sequence, segment.duration),
+            }
+        }
+        report.add_session_stats(session.stats());
+        writer.finish().await?;
+
+        Ok(report)
+    }
+
+    /// 指定した日時まで録音する
+    /// 配信の遅れの分だけ、指定した日時を過ぎてから録音が終わる
+    pub async fn record_until(
+        &self,
+        station_id: &str,
+        end_time: DateTime<Tz>,
+        path: impl AsRef<Path>,
+    ) -> Result<RecordingReport> {
+        let report = RecordingReport::new(
+            station_id,
+            Utc::now().with_timezone(&Tokyo),
+            duration_until(end_time)?,
+        );
+        self.record_with_metadata(report, StopAt::EndTime(end_time), path, None)
+            .await
+    }
+
+    /// 放送中の番組を終了時刻まで録音する
+    /// 番組情報をタグとして書き込む
+    /// 録音結果の`expected`は番組の長さになるため、放送の途中から録音した場合は不足として報告される
+    pub async fn record_program(
+        &self,
+        program: &Program,
+        path: impl AsRef<Path>,
+    ) -> Result<RecordingReport> {
+        // 放送が終了した番組は録音しない
+        duration_until(program.end_time)?;
+        let metadata = self.program_metadata(program).await;
+        let report = RecordingReport::new(
+            &program.station_id,
+            Utc::now().with_timezone(&Tokyo),
+            Duration::from_secs(program.start_to_end_duration()),
+        );
+        self.record_with_metadata(
+            report,
+            StopAt::EndTime(program.end_time),
+            path,
+            Some(metadata),
+        )
+        .await
+    }
+
+    /// 放送済みの番組をタイムフリーで番組全体ダウンロードし、書き出した音声の長さを返す
+    /// 番組情報をタグとして書き込む
+    /// 放送前や放送開始から7日以上経過した番組は`RadikoError::TimefreeUnavailable`になる
+    pub async fn download_timefree(
+        &self,
+        program: &Program,
+        path: impl AsRef<Path>,
+    ) -> Result<Duration> {
+        let segments = self.stream.timefree_segments(program).await?;
+        let metadata = self.program_metadata(program).await;
+        let mut writer = AudioWriter::create(self.output_format, path, Some(metadata)).await?;
+        let mut downloaded = Duration::ZERO;
+        for segment in &segments {
+            writer
+                .write_segment(&self.stream.download_segment(segment).await?)
+                .await?;
+            downloaded += segment.duration;
+        }
+        writer.finish().await?;
+
+        Ok(downloaded)
+    }
+
+    /// 録音ファイルに書き込む番組のタグ
+    /// 放送局名とカバーアートは取得に失敗しても録音を止めないよう、放送局IDとカバーアート無しで代替する
+    /// カバーアートは番組の画像、放送局のロゴの順に取得を試みる
+    pub async fn program_metadata(&self, program: &Program) -> RecordingMetadata {
+        let station = self.find_station(&program.station_id).await;
+        let station_name = station
+            .as_ref()
+            .map_or(program.station_id.as_str(), |station| station.name.as_str());
+        let mut metadata = RecordingMetadata::from_program(program, station_name);
+
+        let logo_url = station.as_ref().and_then(|station| {
+            station
+                .logos
+                .iter()
+                .max_by_key(|logo| logo.width * logo.height)
+                .map(|logo| logo.url.clone())
+        });
+        for url in std::iter::once(program.img.clone())
+            .chain(logo_url)
+            .filter(|url| !url.is_empty())
+        {
+            if let Ok(Some(cover)) = self.fetch_cover_art(&url).await {
+                metadata.cover = Some(cover);
+                break;
+            }
+        }
+
+        metadata
+    }
+
+    /// テンプレートから番組の録音ファイルのパスを作成し、親ディレクトリを作成する
+    /// 放送局の情報が取得できない場合、放送局名は放送局IDで代替する
+    pub async fn program_path(
+        &self,
+        template: &FilenameTemplate,
+        base_dir: impl AsRef<Path>,
+        program: &Program,
+    ) -> Result<PathBuf> {
+        let station = self.find_station(&program.station_id).await;
+        let path = template.resolve(base_dir, program, station.as_ref())?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(path)
+    }
+
+    /// 全国の放送局一覧から放送局を探す
+    async fn find_station(&self, station_id: &str) -> Option<Station> {
+        StationDirectory::new(self.station.stations_all().await.ok()?)
+            .station(station_id)
+            .cloned()
+    }
+
+    async fn fetch_cover_art(&self, url: &str) -> Result<Option<CoverArt>> {
+        let data = ensure_success(self.http.send(self.http.get(url)).await?)
+            .await?
+            .bytes()
+            .await?;
+        Ok(CoverArt::from_bytes(data.to_vec()))
+    }
+}
+
+/// 指定した日時までの録音時間
+fn duration_until(end_time: DateTime<Tz>) -> Result<Duration> {
+    end_time
+        .signed_duration_since(Utc::now())
+        .to_std()
+        .map_err(|_| RadikoError::InvalidArgument(format!("end_time already passed: {}", end_time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        radiko::{EndpointConfig, Radiko, RetryPolicy},
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_media_playlist},
+    };
+    use chrono::TimeZone;
+    use chrono_tz::Asia::Tokyo;
+
+    #[tokio::test]
+    async fn record_live_stream_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(0, 2, false)))
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(1, 3, false)));
+        for sequence in 0..4u8 {
+            let mut segment = adts::tests::id3_tag(b"timestamp");
+            segment.extend([0xff, 0xf1, sequence]);
+            server.route(
+                &format!("/live/segments/{}.aac", sequence),
+                MockResponse::ok(segment),
+            );
+        }
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("live.aac");
+
+        let report = radiko
+            .recorder()
+            .await
+            .record("TBS", Duration::from_secs(15), &path)
+            .await?;
+
+        assert_eq!(report.recorded, Duration::from_secs(15));
+        assert!(report.is_complete());
+        assert_eq!(
+            std::fs::read(&path)?,
+            vec![0xff, 0xf1, 0, 0xff, 0xf1, 1, 0xff, 0xf1, 2]
+        );
+        assert!(server.requests_to("/live/segments/3.aac").is_empty());
+        assert_eq!(
+            server.requests_to("/live/segments/0.aac")[0].header("X-Radiko-Authtoken"),
+            Some("mock_token")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_with_gaps_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(0, 3, false)))
+            // メディアプレイリストのセッションが失効した
+            .route("/live/medialist", MockResponse::status(404))
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(3, 2, true)))
+            .route("/live/segments/1.aac", MockResponse::status(404))
+            .route("/live/segments/2.aac", MockResponse::status(404));
+        for sequence in [0u8, 1, 3, 4] {
+            server.route(
+                &format!("/live/segments/{}.aac", sequence),
+                MockResponse::ok(vec![0xff, 0xf1, sequence]),
+            );
+        }
+        let radiko = Radiko::builder()
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .segment_retry_policy(RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            })
+            .build()
+            .await?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("live.aac");
+
+        let report = radiko
+            .recorder()
+            .await
+            .record("TBS", Duration::from_secs(60), &path)
+            .await?;
+
+        assert_eq!(
+            std::fs::read(&path)?,
+            vec![0xff, 0xf1, 0, 0xff, 0xf1, 1, 0xff, 0xf1, 3, 0xff, 0xf1, 4]
+        );
+        assert_eq!(report.recorded, Duration::from_secs(20));
+        assert_eq!(
+            report.gaps,
+            vec![RecordingGap {
+                first_sequence: 2,
+                last_sequence: 2,
+                start: report.started_at + Duration::from_secs(10),
+                end: report.started_at + Duration::from_secs(15),
+            }]
+        );
+        assert_eq!(report.segment_retries, 2);
+        assert_eq!(report.master_playlist_reloads, 1);
+        assert_eq!(report.reconnects, 0);
+        assert_eq!(report.shortfall(), Duration::from_secs(40));
+        assert!(!report.is_complete());
+        assert_eq!(server.requests_to("/so/playlist.m3u8").len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_reconnect_failure_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            .route("/so/playlist.m3u8", MockResponse::status(404))
+            .route("/live/medialist", MockResponse::status(404));
+        let radiko = Radiko::builder()
+            .endpoint_config(EndpointConfig::single_host(server.base_url()))
+            .reconnect_policy(RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(1),
+            })
+            .build()
+            .await?;
+        let dir = tempfile::tempdir()?;
+
+        let result = radiko
+            .recorder()
+            .await
+            .with_output_format(OutputFormat::M4a)
+            .record("TBS", Duration::from_secs(60), dir.path().join("live.m4a"))
+            .await;
+
+        // 音声を書き出す前に失敗しても、書き出しのエラーではなく通信のエラーを返す
+        assert!(matches!(result, Err(RadikoError::HttpStatus { .. })));
+        // 開始時、メディアプレイリストの404、2回の再接続でマスタープレイリストを取得する
+        assert_eq!(server.requests_to("/so/playlist.m3u8").len(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_until_lagging_playlist_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist\n",
+                    server.base_url()
+                )),
+            )
+            // 最新のセグメントまで20秒分の音声が現在時刻より前の放送になる
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(0, 4, false)))
+            .route("/live/medialist", MockResponse::ok(mock_media_playlist(4, 2, false)));
+        for sequence in 0..6u8 {
+            server.route(
+                &format!("/live/segments/{}.aac", sequence),
+                MockResponse::ok(vec![0xff, 0xf1, sequence]),
+            );
+        }
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("live.aac");
+        let end_time = Utc::now().with_timezone(&Tokyo) + chrono::Duration::seconds(3);
+
+        let report = radiko
+            .recorder()
+            .await
+            .record_until("TBS", end_time, &path)
+            .await?;
+
+        // 現在時刻から3秒分ではなく、放送上の終了日時までのセグメントを録音する
+        assert_eq!(report.recorded, Duration::from_secs(25));
+        assert_eq!(
+            std::fs::read(&path)?,
+            vec![
+                0xff, 0xf1, 0, 0xff, 0xf1, 1, 0xff, 0xf1, 2, 0xff, 0xf1, 3, 0xff, 0xf1, 4
+            ]
+        );
+        assert!(server.requests_to("/live/segments/5.aac").is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn record_ended_program_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let end_time = Utc::now().with_timezone(&Tokyo) - chrono::Duration::minutes(1);
+
+        let result = radiko
+            .recorder()
+            .await
+            .record_until("TBS", end_time, "ended.aac")
+            .await;
+
+        assert!(matches!(result, Err(RadikoError::InvalidArgument(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_timefree_m4a_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/tf/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/tf/chunklist.m3u8\n",
+                    server.base_url()
+                )),
+            )
+            .route(
+                "/tf/chunklist.m3u8",
+                MockResponse::ok(mock_media_playlist(0, 2, true)),
+            );
+        for sequence in 0..2u8 {
+            let mut segment = adts::tests::id3_tag(b"timestamp");
+            segment.extend(adts::tests::adts_frame(&[sequence; 4]));
+            server.route(
+                &format!("/tf/segments/{}.aac", sequence),
+                MockResponse::ok(segment),
+            );
+        }
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let program = past_program(
+            Utc::now().with_timezone(&Tokyo) - chrono::Duration::days(1),
+            5,
+        );
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("timefree.m4a");
+
+        radiko
+            .recorder()
+            .await
+            .with_output_format(OutputFormat::M4a)
+            .download_timefree(&program, &path)
+            .await?;
+
+        let data = std::fs::read(&path)?;
+        assert_eq!(&data[4..8], b"ftyp");
+        assert_eq!(
+            mp4::tests::find_box(&data, &[b"mdat"]).unwrap(),
+            [[0u8; 4], [1u8; 4]].concat()
+        );
+        Ok(())
+    }
+
+    fn past_program(start_time: DateTime<Tz>, minutes: i64) -> Program {
+        Program {
+            title: "timefree".to_string(),
+            ..Program::fixture(start_time, start_time + chrono::Duration::minutes(minutes))
+        }
+    }
+
+    #[tokio::test]
+    async fn download_timefree_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let master_playlist = |chunk: &str| {
+            MockResponse::ok(format!(
+                "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/tf/{}/chunklist.m3u8\n",
+                server.base_url(),
+                chunk
+            ))
+        };
+        server
+            .route("/tf/playlist.m3u8", master_playlist("1"))
+            .route("/tf/playlist.m3u8", master_playlist("2"))
+            .route(
+                "/tf/1/chunklist.m3u8",
+                MockResponse::ok(mock_media_playlist(0, 2, true)),
+            )
+            .route(
+                "/tf/2/chunklist.m3u8",
+                MockResponse::ok(mock_media_playlist(0, 2, true)),
+            );
+        for (chunk, sequence) in [(1u8, 0u8), (1, 1), (2, 0), (2, 1)] {
+            let mut segment = adts::tests::id3_tag(b"timestamp");
+            segment.extend([0xff, 0xf1, chunk, sequence]);
+            server.route(
+                &format!("/tf/{}/segments/{}.aac", chunk, sequence),
+                MockResponse::ok(segment),
+            );
+        }
+        server
+            .route(
+                "/v3/station/region/full.xml",
+                MockResponse::ok(format!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<region>
+  <stations ascii_name="KANTO" region_id="kanto" region_name="関東">
+    <station>
+      <id>TBS</id><name>TBSラジオ</name><ascii_name>TBS RADIO</ascii_name><ruby>てぃーびーえすらじお</ruby>
+      <areafree>1</areafree><timefree>1</timefree>
+      <logo width="224" height="100" align="lrtrim">{base}/logo/small.png</logo>
+      <logo width="448" height="200" align="lrtrim">{base}/logo/large.png</logo>
+      <tf_max_delay>90</tf_max_delay><banner></banner><area_id>JP13</area_id>
+      <href>https://www.tbsradio.jp/</href><simul_max_delay>15</simul_max_delay>
+    </station>
+  </stations>
+</region>
+"#,
+                    base = server.base_url()
+                )),
+            )
+            .route(
+                "/logo/large.png",
+                MockResponse::ok(b"\x89PNG\r\n\x1a\nlogo".to_vec()),
+            );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let program = past_program(
+            Utc::now().with_timezone(&Tokyo) - chrono::Duration::days(1),
+            10,
+        );
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("timefree.aac");
+
+        let downloaded = radiko
+            .recorder()
+            .await
+            .download_timefree(&program, &path)
+            .await?;
+
+        assert_eq!(downloaded, Duration::from_secs(20));
+        let data = std::fs::read(&path)?;
+        assert_eq!(
+            adts::strip_id3(&data),
+            vec![
+                0xff, 0xf1, 1, 0, 0xff, 0xf1, 1, 1, 0xff, 0xf1, 2, 0, 0xff, 0xf1, 2, 1
+            ]
+        );
+        // 番組情報と放送局のロゴがID3タグとして書き込まれる
+        let album = "TBSラジオ".as_bytes();
+        assert!(data.windows(album.len()).any(|window| window == album));
+        assert!(data.windows(10).any(|window| window == b"image/png\0"));
+        let playlist_requests = server.requests_to("/tf/playlist.m3u8");
+        assert_eq!(playlist_requests.len(), 2);
+        let seek = (program.start_time + chrono::Duration::minutes(5))
+            .format("%Y%m%d%H%M%S")
+            .to_string();
+        assert!(
+            playlist_requests[1]
+                .target
+                .contains(&format!("seek={}", seek))
+        );
+
+        // 7日より前の番組は取得しない
+        let old_program = past_program(Tokyo.with_ymd_and_hms(2025, 7, 1, 1, 0, 0).unwrap(), 10);
+        let result = radiko
+            .recorder()
+            .await
+            .download_timefree(&old_program, &path)
+            .await;
+        assert!(matches!(result, Err(RadikoError::TimefreeUnavailable(_))));
+        Ok(())
+    }
+}
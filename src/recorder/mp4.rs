@@ -0,0 +1,559 @@
+//! ADTSから取り出したAACのフレームをMP4コンテナ(`.m4a`)に格納する
+//! ffmpegの`aac_adtstoasc`と同様にADTSヘッダーを取り除き、デコーダー設定はesdsに書き込む
+//! 再生アプリがダウンロード途中から再生やシークできるよう、moovをmdatの前に配置する
+
+use std::path::{Path, PathBuf};
+
+use tempfile::NamedTempFile;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+};
+
+use crate::error::{RadikoError, Result};
+
+use super::{
+    adts::{AdtsReader, AudioConfig, SAMPLES_PER_FRAME},
+    metadata::{ImageFormat, RecordingMetadata},
+};
+
+const TRACK_ID: u32 = 1;
+/// ISO 639-2/Tの"und"を5bitずつ詰めた値
+const LANGUAGE_UNDETERMINED: u16 = 0x55c4;
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+/// ilstのdataに格納する値の種類
+const DATA_TYPE_UTF8: u32 = 1;
+const DATA_TYPE_JPEG: u32 = 13;
+const DATA_TYPE_PNG: u32 = 14;
+
+/// 録音中のAACフレームを一時ファイルに書き出し、完了時にMP4の`.m4a`ファイルにする
+/// moovの作成には全フレームのサイズが必要なため、`finish`を呼ぶまで出力先には何も書き込まない
+pub struct M4aWriter {
+    path: PathBuf,
+    metadata: Option<RecordingMetadata>,
+    reader: AdtsReader,
+    config: Option<AudioConfig>,
+    sample_sizes: Vec<u32>,
+    data_len: u64,
+    /// ADTSヘッダーを取り除いたフレームを連結した一時ファイル
+    samples: NamedTempFile,
+    samples_writer: BufWriter<File>,
+}
+
+impl M4aWriter {
+    /// 一時ファイルは出力先と同じディレクトリに作成する
+    /// metadataはmoovのudtaにiTunes形式のタグとして書き込む
+    pub async fn create(
+        path: impl AsRef<Path>,
+        metadata: Option<RecordingMetadata>,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let samples = NamedTempFile::new_in(output_dir(&path))?;
+        let samples_writer = BufWriter::new(File::from_std(samples.reopen()?));
+
+        Ok(Self {
+            path,
+            metadata,
+            reader: AdtsReader::default(),
+            config: None,
+            sample_sizes: Vec::new(),
+            data_len: 0,
+            samples,
+            samples_writer,
+        })
+    }
+
+    /// ID3タグを取り除いたADTSのデータを追加する
+    pub async fn write(&mut self, adts: &[u8]) -> Result<()> {
+        for frame in self.reader.push(adts)? {
+            match self.config {
+                None => self.config = Some(frame.config),
+                Some(config) if config != frame.config => {
+                    return Err(RadikoError::InvalidAudio(format!(
+                        "audio config changed during recording: {:?} -> {:?}",
+                        config, frame.config
+                    )));
+                }
+                Some(_) => {}
+            }
+            self.samples_writer.write_all(&frame.payload).await?;
+            self.sample_sizes.push(frame.payload.len() as u32);
+            self.data_len += frame.payload.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// moovとmdatを書き出してファイルを完成させる
+    pub async fn finish(mut self) -> Result<()> {
+        self.samples_writer.flush().await?;
+        let config = self
+            .config
+            .ok_or_else(|| RadikoError::InvalidAudio("no AAC frames were recorded".to_string()))?;
+
+        let header = file_header(
+            &config,
+            &self.sample_sizes,
+            self.data_len,
+            self.metadata.as_ref(),
+        )?;
+        let output = NamedTempFile::new_in(output_dir(&self.path))?;
+        let mut writer = BufWriter::new(File::from_std(output.reopen()?));
+        writer.write_all(&header).await?;
+        tokio::io::copy(&mut File::open(self.samples.path()).await?, &mut writer).await?;
+        writer.flush().await?;
+        writer.into_inner().sync_all().await?;
+        output.persist(&self.path).map_err(|err| err.error)?;
+
+        Ok(())
+    }
+}
+
+fn output_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// ftyp、moov、mdatのヘッダーを連結したファイルの先頭部分
+/// mdatの中身(フレームの連結)はこの直後に続ける
+pub fn file_header(
+    config: &AudioConfig,
+    sample_sizes: &[u32],
+    data_len: u64,
+    metadata: Option<&RecordingMetadata>,
+) -> Result<Vec<u8>> {
+    let ftyp = mp4_box(
+        b"ftyp",
+        &[
+            b"M4A ",
+            &0x200u32.to_be_bytes()[..],
+            b"M4A ",
+            b"mp42",
+            b"isom",
+        ]
+        .concat(),
+    );
+    let mdat_header = mdat_header(data_len);
+    // stcoのオフセットの値はmoovのサイズに影響しないので、仮の値で作成してサイズを求める
+    let moov_len = moov(config, sample_sizes, data_len, 0, metadata)?.len();
+    let data_offset = u32::try_from(ftyp.len() + moov_len + mdat_header.len())
+        .map_err(|_| RadikoError::InvalidAudio("moov box is too large".to_string()))?;
+    let moov = moov(config, sample_sizes, data_len, data_offset, metadata)?;
+
+    Ok([ftyp, moov, mdat_header].concat())
+}
+
+fn mdat_header(data_len: u64) -> Vec<u8> {
+    match u32::try_from(data_len + 8) {
+        Ok(size) => [&size.to_be_bytes()[..], b"mdat"].concat(),
+        // 4GBを超える場合はlargesizeを使う
+        Err(_) => [
+            &1u32.to_be_bytes()[..],
+            b"mdat",
+            &(data_len + 16).to_be_bytes(),
+        ]
+        .concat(),
+    }
+}
+
+fn moov(
+    config: &AudioConfig,
+    sample_sizes: &[u32],
+    data_len: u64,
+    data_offset: u32,
+    metadata: Option<&RecordingMetadata>,
+) -> Result<Vec<u8>> {
+    let timescale = config.sample_rate();
+    let duration = u32::try_from(sample_sizes.len() as u64 * SAMPLES_PER_FRAME as u64)
+        .map_err(|_| RadikoError::InvalidAudio("recording is too long".to_string()))?;
+
+    let mvhd = full_box(
+        b"mvhd",
+        0,
+        0,
+        &[
+            &[0u8; 8][..],
+            &timescale.to_be_bytes(),
+            &duration.to_be_bytes(),
+            &0x0001_0000u32.to_be_bytes(),
+            &0x0100u16.to_be_bytes(),
+            &[0u8; 10],
+            &matrix(),
+            &[0u8; 24],
+            &(TRACK_ID + 1).to_be_bytes(),
+        ]
+        .concat(),
+    );
+    let tkhd = full_box(
+        b"tkhd",
+        0,
+        // track_enabled | track_in_movie | track_in_preview
+        0x07,
+        &[
+            &[0u8; 8][..],
+            &TRACK_ID.to_be_bytes(),
+            &[0u8; 4],
+            &duration.to_be_bytes(),
+            &[0u8; 12],
+            &0x0100u16.to_be_bytes(),
+            &[0u8; 2],
+            &matrix(),
+            &[0u8; 8],
+        ]
+        .concat(),
+    );
+    let mdhd = full_box(
+        b"mdhd",
+        0,
+        0,
+        &[
+            &[0u8; 8][..],
+            &timescale.to_be_bytes(),
+            &duration.to_be_bytes(),
+            &LANGUAGE_UNDETERMINED.to_be_bytes(),
+            &[0u8; 2],
+        ]
+        .concat(),
+    );
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &[&[0u8; 4][..], b"soun", &[0u8; 12], b"SoundHandler\0"].concat(),
+    );
+    let smhd = full_box(b"smhd", 0, 0, &[0u8; 4]);
+    let dinf = mp4_box(
+        b"dinf",
+        &full_box(
+            b"dref",
+            0,
+            0,
+            &[&1u32.to_be_bytes()[..], &full_box(b"url ", 0, 0x01, &[])].concat(),
+        ),
+    );
+    let stbl = mp4_box(
+        b"stbl",
+        &[
+            stsd(config, sample_sizes, data_len),
+            full_box(
+                b"stts",
+                0,
+                0,
+                &[
+                    &1u32.to_be_bytes()[..],
+                    &(sample_sizes.len() as u32).to_be_bytes(),
+                    &SAMPLES_PER_FRAME.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            // 全てのフレームを1つのチャンクにまとめる
+            full_box(
+                b"stsc",
+                0,
+                0,
+                &[
+                    &1u32.to_be_bytes()[..],
+                    &1u32.to_be_bytes(),
+                    &(sample_sizes.len() as u32).to_be_bytes(),
+                    &1u32.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            full_box(
+                b"stsz",
+                0,
+                0,
+                &[
+                    &0u32.to_be_bytes()[..],
+                    &(sample_sizes.len() as u32).to_be_bytes(),
+                    &sample_sizes
+                        .iter()
+                        .flat_map(|size| size.to_be_bytes())
+                        .collect::<Vec<_>>(),
+                ]
+                .concat(),
+            ),
+            full_box(
+                b"stco",
+                0,
+                0,
+                &[&1u32.to_be_bytes()[..], &data_offset.to_be_bytes()].concat(),
+            ),
+        ]
+        .concat(),
+    );
+    let minf = mp4_box(b"minf", &[smhd, dinf, stbl].concat());
+    let mdia = mp4_box(b"mdia", &[mdhd, hdlr, minf].concat());
+    let trak = mp4_box(b"trak", &[tkhd, mdia].concat());
+
+    let udta = metadata.map(udta).unwrap_or_default();
+
+    Ok(mp4_box(b"moov", &[mvhd, trak, udta].concat()))
+}
+
+/// iTunes形式のタグ(udta/meta/ilst)
+fn udta(metadata: &RecordingMetadata) -> Vec<u8> {
+    let mut items = Vec::new();
+    for (kind, text) in [
+        (b"\xa9nam", &metadata.title),
+        (b"\xa9ART", &metadata.artist),
+        (b"\xa9alb", &metadata.album),
+        (b"\xa9day", &metadata.date),
+        (b"\xa9cmt", &metadata.comment),
+    ] {
+        if !text.is_empty() {
+            items.extend(ilst_item(kind, DATA_TYPE_UTF8, text.as_bytes()));
+        }
+    }
+    if let Some(cover) = &metadata.cover {
+        let data_type = match cover.format {
+            ImageFormat::Jpeg => DATA_TYPE_JPEG,
+            ImageFormat::Png => DATA_TYPE_PNG,
+        };
+        items.extend(ilst_item(b"covr", data_type, &cover.data));
+    }
+    if items.is_empty() {
+        return items;
+    }
+
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &[&[0u8; 4][..], b"mdir", b"appl", &[0u8; 8], &[0x00]].concat(),
+    );
+    let meta = full_box(b"meta", 0, 0, &[hdlr, mp4_box(b"ilst", &items)].concat());
+    mp4_box(b"udta", &meta)
+}
+
+fn ilst_item(kind: &[u8; 4], data_type: u32, value: &[u8]) -> Vec<u8> {
+    mp4_box(
+        kind,
+        &mp4_box(
+            b"data",
+            &[&data_type.to_be_bytes()[..], &[0u8; 4], value].concat(),
+        ),
+    )
+}
+
+fn stsd(config: &AudioConfig, sample_sizes: &[u32], data_len: u64) -> Vec<u8> {
+    let sample_rate = config.sample_rate();
+    let channel_count = match config.channel_config {
+        0 => 2,
+        channel_config => channel_config as u16,
+    };
+    let max_sample_size = sample_sizes.iter().copied().max().unwrap_or_default();
+    let duration_secs =
+        (sample_sizes.len() as u64 * SAMPLES_PER_FRAME as u64) as f64 / sample_rate as f64;
+    let avg_bitrate = if duration_secs > 0.0 {
+        (data_len as f64 * 8.0 / duration_secs) as u32
+    } else {
+        0
+    };
+    let max_bitrate =
+        (max_sample_size as u64 * 8 * sample_rate as u64 / SAMPLES_PER_FRAME as u64) as u32;
+
+    let decoder_specific_info = descriptor(0x05, &config.audio_specific_config());
+    let decoder_config = descriptor(
+        0x04,
+        &[
+            // objectTypeIndication: Audio ISO/IEC 14496-3
+            &[0x40u8][..],
+            // streamType: AudioStream, upStream: 0, reserved: 1
+            &[0x15],
+            &max_sample_size.to_be_bytes()[1..],
+            &max_bitrate.to_be_bytes(),
+            &avg_bitrate.to_be_bytes(),
+            &decoder_specific_info,
+        ]
+        .concat(),
+    );
+    let sl_config = descriptor(0x06, &[0x02]);
+    let es_descriptor = descriptor(
+        0x03,
+        &[
+            &(TRACK_ID as u16).to_be_bytes()[..],
+            &[0x00],
+            &decoder_config,
+            &sl_config,
+        ]
+        .concat(),
+    );
+    let esds = full_box(b"esds", 0, 0, &es_descriptor);
+    let mp4a = mp4_box(
+        b"mp4a",
+        &[
+            &[0u8; 6][..],
+            // data_reference_index
+            &1u16.to_be_bytes(),
+            &[0u8; 8],
+            &channel_count.to_be_bytes(),
+            // samplesize
+            &16u16.to_be_bytes(),
+            &[0u8; 4],
+            &(sample_rate.min(u16::MAX as u32) << 16).to_be_bytes(),
+            &esds,
+        ]
+        .concat(),
+    );
+
+    full_box(b"stsd", 0, 0, &[&1u32.to_be_bytes()[..], &mp4a].concat())
+}
+
+fn matrix() -> Vec<u8> {
+    UNITY_MATRIX
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect()
+}
+
+pub(crate) fn mp4_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    [
+        &((payload.len() + 8) as u32).to_be_bytes()[..],
+        kind,
+        payload,
+    ]
+    .concat()
+}
+
+pub(crate) fn full_box(kind: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+    mp4_box(
+        kind,
+        &[&[version][..], &flags.to_be_bytes()[1..], payload].concat(),
+    )
+}
+
+/// MPEG-4 Systemsのディスクリプタ
+/// radikoの音声では127byteを超えることはないため、長さは1byteで表す
+fn descriptor(tag: u8, payload: &[u8]) -> Vec<u8> {
+    [&[tag, payload.len() as u8][..], payload].concat()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::recorder::{adts::tests::adts_frame, metadata::CoverArt};
+
+    /// boxの種類を辿って中身を返す
+    pub(crate) fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+        let (kind, rest) = path.split_first()?;
+        let mut position = 0;
+        while position + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[position..position + 4].try_into().ok()?) as usize;
+            if size < 8 || position + size > data.len() {
+                return None;
+            }
+            if &data[position + 4..position + 8] == *kind {
+                let payload = &data[position + 8..position + size];
+                return if rest.is_empty() {
+                    Some(payload)
+                } else {
+                    // stsd、meta、mp4aは子boxの前に固定長のフィールドを持つ
+                    let skip = match *kind {
+                        b"stsd" => 8,
+                        b"meta" => 4,
+                        b"mp4a" => 28,
+                        _ => 0,
+                    };
+                    find_box(&payload[skip..], rest)
+                };
+            }
+            position += size;
+        }
+        None
+    }
+
+    fn top_level_kinds(data: &[u8]) -> Vec<String> {
+        let mut kinds = Vec::new();
+        let mut position = 0;
+        while position + 8 <= data.len() {
+            let size = u32::from_be_bytes(data[position..position + 4].try_into().unwrap());
+            kinds.push(String::from_utf8_lossy(&data[position + 4..position + 8]).to_string());
+            position += size as usize;
+        }
+        kinds
+    }
+
+    #[tokio::test]
+    async fn write_m4a_test() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("recorded.m4a");
+        let mut stream = adts_frame(b"frame1");
+        stream.extend(adts_frame(b"frame02"));
+        stream.extend(adts_frame(b"frame003"));
+        let (first, second) = stream.split_at(20);
+
+        let metadata = RecordingMetadata {
+            title: "番組".to_string(),
+            cover: Some(CoverArt {
+                format: ImageFormat::Jpeg,
+                data: vec![0xff, 0xd8, 0xff, 0xe0],
+            }),
+            ..Default::default()
+        };
+        let mut writer = M4aWriter::create(&path, Some(metadata)).await?;
+        writer.write(first).await?;
+        writer.write(second).await?;
+        writer.finish().await?;
+
+        let data = std::fs::read(&path)?;
+        assert_eq!(top_level_kinds(&data), vec!["ftyp", "moov", "mdat"]);
+        assert_eq!(
+            find_box(&data, &[b"mdat"]).unwrap(),
+            b"frame1frame02frame003"
+        );
+
+        let mdhd = find_box(&data, &[b"moov", b"trak", b"mdia", b"mdhd"]).unwrap();
+        let timescale = u32::from_be_bytes(mdhd[12..16].try_into()?);
+        let duration = u32::from_be_bytes(mdhd[16..20].try_into()?);
+        assert_eq!(timescale, 24000);
+        assert_eq!(duration, 3 * 1024);
+
+        let stbl = [b"moov", b"trak", b"mdia", b"minf", b"stbl"];
+        let stsz = find_box(&data, &[&stbl[..], &[b"stsz"]].concat()).unwrap();
+        assert_eq!(&stsz[8..12], 3u32.to_be_bytes());
+        assert_eq!(&stsz[12..], [6u32, 7, 8].map(u32::to_be_bytes).concat());
+
+        let stco = find_box(&data, &[&stbl[..], &[b"stco"]].concat()).unwrap();
+        let offset = u32::from_be_bytes(stco[8..12].try_into()?) as usize;
+        assert_eq!(&data[offset..offset + 6], b"frame1");
+
+        let esds = find_box(&data, &[&stbl[..], &[b"stsd", b"mp4a", b"esds"]].concat()).unwrap();
+        assert!(
+            esds.windows(4)
+                .any(|window| window == [0x05, 0x02, 0x13, 0x10])
+        );
+
+        let ilst = [b"moov", b"udta", b"meta"];
+        let title = find_box(
+            &data,
+            &[&ilst[..], &[b"ilst", b"\xa9nam", b"data"]].concat(),
+        )
+        .unwrap();
+        assert_eq!(
+            title,
+            [&1u32.to_be_bytes()[..], &[0u8; 4], "番組".as_bytes()].concat()
+        );
+        let cover = find_box(&data, &[&ilst[..], &[b"ilst", b"covr", b"data"]].concat()).unwrap();
+        assert_eq!(&cover[..4], 13u32.to_be_bytes());
+        assert!(find_box(&data, &[&ilst[..], &[b"ilst", b"\xa9ART"]].concat()).is_none());
+
+        // 一時ファイルは残らない
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn write_empty_m4a_test() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("empty.m4a");
+
+        let writer = M4aWriter::create(&path, None).await?;
+        let result = writer.finish().await;
+
+        assert!(matches!(result, Err(RadikoError::InvalidAudio(_))));
+        assert!(!path.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono_tz::Tz;
+
+use crate::api::hls::HlsSessionStats;
+
+/// ライブ配信の録音結果
+/// 欠落したセグメントや再試行の回数を記録し、録音が完全かどうかを後から確認できるようにする
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingReport {
+    pub station_id: String,
+    /// 録音を開始した日時
+    pub started_at: DateTime<Tz>,
+    /// 書き出した音声の長さ
+    pub recorded: Duration,
+    /// 録音するはずだった長さ
+    /// 番組を録音した場合は`Program::start_to_end_duration()`
+    pub expected: Duration,
+    pub gaps: Vec<RecordingGap>,
+    /// セグメントのダウンロードを再試行した回数
+    pub segment_retries: u32,
+    /// メディアプレイリストが404になり、マスタープレイリストを取得し直した回数
+    pub master_playlist_reloads: u32,
+    /// プレイリストの取得に失敗し、マスタープレイリストから接続し直した回数
+    pub reconnects: u32,
+}
+
+/// 録音できなかった連続するセグメント
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordingGap {
+    pub first_sequence: u64,
+    pub last_sequence: u64,
+    /// 録音の開始日時と音声の長さから推定した、欠落した区間の日時
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+}
+
+impl RecordingGap {
+    pub fn missing_segments(&self) -> u64 {
+        self.last_sequence - self.first_sequence + 1
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.end
+            .signed_duration_since(self.start)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+impl RecordingReport {
+    pub(crate) fn new(station_id: &str, started_at: DateTime<Tz>, expected: Duration) -> Self {
+        Self {
+            station_id: station_id.to_string(),
+            started_at,
+            recorded: Duration::ZERO,
+            expected,
+            gaps: Vec::new(),
+            segment_retries: 0,
+            master_playlist_reloads: 0,
+            reconnects: 0,
+        }
+    }
+
+    /// 欠落した音声の長さの合計
+    pub fn missing(&self) -> Duration {
+        self.gaps.iter().map(RecordingGap::duration).sum()
+    }
+
+    /// 録音するはずだった長さに対して不足している長さ
+    pub fn shortfall(&self) -> Duration {
+        self.expected.saturating_sub(self.recorded)
+    }
+
+    /// 欠落が無く、録音するはずだった長さを全て書き出せた場合にtrue
+    pub fn is_complete(&self) -> bool {
+        self.gaps.is_empty() && self.shortfall().is_zero()
+    }
+
+    /// 録音の開始から経過した音声上の時間
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.recorded + self.missing()
+    }
+
+    /// 欠落したセグメントを記録する。直前の欠落と連続している場合はまとめる
+    pub(crate) fn push_gap(&mut self, first_sequence: u64, last_sequence: u64, duration: Duration) {
+        let start = self.started_at + self.elapsed();
+        let end = start + duration;
+        if let Some(gap) = self.gaps.last_mut()
+            && gap.last_sequence + 1 == first_sequence
+            && gap.end == start
+        {
+            gap.last_sequence = last_sequence;
+            gap.end = end;
+            return;
+        }
+        self.gaps.push(RecordingGap {
+            first_sequence,
+            last_sequence,
+            start,
+            end,
+        });
+    }
+
+    pub(crate) fn add_session_stats(&mut self, stats: HlsSessionStats) {
+        self.segment_retries += stats.segment_retries;
+        self.master_playlist_reloads += stats.master_playlist_reloads;
+        self.reconnects += stats.reconnects;
+    }
+}
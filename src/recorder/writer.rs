@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+};
+
+use crate::error::Result;
+
+use super::{OutputFormat, adts, id3, metadata::RecordingMetadata, mp4::M4aWriter};
+
+/// 出力形式に応じてセグメントを書き出す
+pub enum AudioWriter {
+    Adts(BufWriter<File>),
+    M4a(Box<M4aWriter>),
+}
+
+impl AudioWriter {
+    /// metadataがある場合は出力形式に応じたタグを書き込む
+    pub async fn create(
+        format: OutputFormat,
+        path: impl AsRef<Path>,
+        metadata: Option<RecordingMetadata>,
+    ) -> Result<Self> {
+        Ok(match format {
+            OutputFormat::Adts => {
+                let mut writer = BufWriter::new(File::create(path).await?);
+                if let Some(metadata) = &metadata {
+                    writer.write_all(&id3::tag(metadata)).await?;
+                }
+                Self::Adts(writer)
+            }
+            OutputFormat::M4a => Self::M4a(Box::new(M4aWriter::create(path, metadata).await?)),
+        })
+    }
+
+    /// ダウンロードしたセグメントからID3タグを取り除いて書き出す
+    pub async fn write_segment(&mut self, segment: &[u8]) -> Result<()> {
+        let data = adts::strip_id3(segment);
+        match self {
+            Self::Adts(writer) => writer.write_all(data).await?,
+            Self::M4a(writer) => writer.write(data).await?,
+        }
+        Ok(())
+    }
+
+    pub async fn finish(self) -> Result<()> {
+        match self {
+            Self::Adts(mut writer) => writer.flush().await?,
+            Self::M4a(writer) => writer.finish().await?,
+        }
+        Ok(())
+    }
+}
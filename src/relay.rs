@@ -0,0 +1,332 @@
+//! `X-Radiko-Authtoken`ヘッダーを付与できないプレイヤー向けに、認証を代行するHLSのリレーサーバー
+//! VLCやmpv、Sonosなどで`http://localhost:PORT/live/{station_id}.m3u8`を開くと、
+//! プレイリストとセグメントを認証付きで取得して中継する。認証トークンの失効時は自動で再認証する
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::net::TcpListener;
+
+use crate::{
+    api::{hls::resolve_url, stream::RadikoStream},
+    error::{RadikoError, Result},
+};
+
+const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+const SEGMENT_CONTENT_TYPE: &str = "audio/aac";
+const PLAYLIST_PATH: &str = "/playlist";
+const SEGMENT_PATH: &str = "/segment";
+/// 中継したプレイリストに最後に含まれてから、そのホストへの中継を許可する時間
+/// ライブのプレイリストは数秒ごとに再取得されるので、視聴中のホストは期限切れにならない
+const ALLOWED_HOST_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// `Radiko::relay`で取得する
+#[derive(Clone)]
+pub struct RadikoRelay {
+    inner: Arc<RadikoRelayRef>,
+}
+
+struct RadikoRelayRef {
+    stream: RadikoStream,
+    /// 中継したプレイリストに含まれていたホストと、最後に含まれていた時刻
+    /// 任意のURLに認証ヘッダーを付けて取得するオープンプロキシにならないよう、これ以外への中継は拒否する
+    /// `ALLOWED_HOST_TTL`を過ぎたホストは拒否し、次に追加する際に取り除く
+    allowed_hosts: RwLock<HashMap<String, Instant>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelayQuery {
+    url: String,
+}
+
+impl RadikoRelay {
+    pub(crate) fn new(stream: RadikoStream) -> Self {
+        Self {
+            inner: Arc::new(RadikoRelayRef {
+                stream,
+                allowed_hosts: RwLock::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// 既存のaxumアプリケーションに組み込む場合に使う
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/live/{file}", get(live_playlist))
+            .route(PLAYLIST_PATH, get(playlist))
+            .route(SEGMENT_PATH, get(segment))
+            .with_state(self.clone())
+    }
+
+    /// listenerで待ち受けを開始する。LANの他の機器から使う場合は`0.0.0.0`でbindする
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        axum::serve(listener, self.router()).await?;
+        Ok(())
+    }
+
+    /// プレイリストを取得し、URIをこのサーバー経由に書き換える
+    async fn relay_playlist(&self, url: &str) -> Result<String> {
+        let content = self.inner.stream.send_authed(url).await?.text().await?;
+
+        let mut is_variant_stream = false;
+        let mut lines = Vec::new();
+        for line in content.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                is_variant_stream |= trimmed.starts_with("#EXT-X-STREAM-INF");
+                lines.push(line.to_string());
+                continue;
+            }
+            let path = if is_variant_stream {
+                PLAYLIST_PATH
+            } else {
+                SEGMENT_PATH
+            };
+            lines.push(self.relay_uri(path, &resolve_url(url, trimmed)?)?);
+            is_variant_stream = false;
+        }
+
+        Ok(lines.join("\n") + "\n")
+    }
+
+    fn relay_uri(&self, path: &str, url: &str) -> Result<String> {
+        let host = host_of(url)
+            .ok_or_else(|| RadikoError::PlaylistParse(format!("invalid uri: {}", url)))?;
+        let now = Instant::now();
+        let mut allowed_hosts = self
+            .inner
+            .allowed_hosts
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        allowed_hosts.retain(|_, last_seen| now.duration_since(*last_seen) < ALLOWED_HOST_TTL);
+        allowed_hosts.insert(host, now);
+        drop(allowed_hosts);
+
+        let mut relay_url = Url::parse("http://localhost").expect("valid url");
+        relay_url.set_path(path);
+        relay_url.query_pairs_mut().append_pair("url", url);
+        Ok(format!(
+            "{}?{}",
+            relay_url.path(),
+            relay_url.query().unwrap_or_default()
+        ))
+    }
+
+    fn is_allowed(&self, url: &str) -> bool {
+        host_of(url).is_some_and(|host| {
+            self.inner
+                .allowed_hosts
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .get(&host)
+                .is_some_and(|last_seen| last_seen.elapsed() < ALLOWED_HOST_TTL)
+        })
+    }
+}
+
+fn host_of(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    Some(format!(
+        "{}:{}",
+        url.host_str()?,
+        url.port_or_known_default()?
+    ))
+}
+
+async fn live_playlist(State(relay): State<RadikoRelay>, Path(file): Path<String>) -> Response {
+    let Some(station_id) = file.strip_suffix(".m3u8").filter(|station_id| {
+        !station_id.is_empty()
+            && station_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let stream_url = match relay.inner.stream.stream_url(station_id).await {
+        Ok(stream_url) => stream_url,
+        Err(err) => return error_response(err),
+    };
+    playlist_response(relay.relay_playlist(&stream_url).await)
+}
+
+async fn playlist(State(relay): State<RadikoRelay>, Query(query): Query<RelayQuery>) -> Response {
+    if !relay.is_allowed(&query.url) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    playlist_response(relay.relay_playlist(&query.url).await)
+}
+
+async fn segment(State(relay): State<RadikoRelay>, Query(query): Query<RelayQuery>) -> Response {
+    if !relay.is_allowed(&query.url) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let response = match relay.inner.stream.send_authed(&query.url).await {
+        Ok(response) => response,
+        Err(err) => return error_response(err),
+    };
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(SEGMENT_CONTENT_TYPE)
+        .to_string();
+    match response.bytes().await {
+        Ok(body) => ([(header::CONTENT_TYPE, content_type)], body).into_response(),
+        Err(err) => error_response(err.into()),
+    }
+}
+
+fn playlist_response(playlist: Result<String>) -> Response {
+    match playlist {
+        Ok(playlist) => ([(header::CONTENT_TYPE, PLAYLIST_CONTENT_TYPE)], playlist).into_response(),
+        Err(err) => error_response(err),
+    }
+}
+
+/// radiko側のエラーは404以外を502にする
+fn error_response(err: RadikoError) -> Response {
+    let status = match &err {
+        RadikoError::HttpStatus { status, .. } if status.as_u16() == 404 => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    (status, err.to_string()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        radiko::{EndpointConfig, Radiko},
+        test_server::{MockResponse, MockServer, mock_auth_routes, mock_media_playlist},
+    };
+
+    #[tokio::test]
+    async fn relay_live_stream_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        server
+            .route(
+                "/so/playlist.m3u8",
+                MockResponse::ok(format!(
+                    "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=52973,CODECS=\"mp4a.40.5\"\n{}/live/medialist?session=abc\n",
+                    server.base_url()
+                )),
+            )
+            .route(
+                "/live/medialist",
+                MockResponse::ok(mock_media_playlist(0, 1, false)),
+            )
+            .route(
+                "/live/segments/0.aac",
+                MockResponse::ok(b"segment".to_vec()),
+            );
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let relay_url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(radiko.relay().await.serve(listener));
+        let client = reqwest::Client::new();
+
+        let master_playlist = client
+            .get(format!("{}/live/TBS.m3u8", relay_url))
+            .send()
+            .await?
+            .text()
+            .await?;
+        let media_playlist_uri = master_playlist.lines().last().unwrap();
+        assert!(media_playlist_uri.starts_with("/playlist?url="));
+
+        let media_playlist = client
+            .get(format!("{}{}", relay_url, media_playlist_uri))
+            .send()
+            .await?
+            .text()
+            .await?;
+        let segment_uri = media_playlist
+            .lines()
+            .find(|line| line.starts_with(SEGMENT_PATH))
+            .unwrap();
+        assert!(media_playlist.contains("#EXTINF:5,"));
+
+        let segment = client
+            .get(format!("{}{}", relay_url, segment_uri))
+            .send()
+            .await?
+            .bytes()
+            .await?;
+        assert_eq!(&segment[..], b"segment");
+        assert_eq!(
+            server.requests_to("/live/segments/0.aac")[0].header("X-Radiko-Authtoken"),
+            Some("mock_token")
+        );
+        assert_eq!(
+            server.requests_to("/live/medialist")[0].target,
+            "/live/medialist?session=abc"
+        );
+
+        // プレイリストに含まれていないホストへは中継しない
+        let status = client
+            .get(format!(
+                "{}/segment?url=https://example.com/secret",
+                relay_url
+            ))
+            .send()
+            .await?
+            .status();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let status = client
+            .get(format!("{}/live/..%2Fetc.m3u8", relay_url))
+            .send()
+            .await?
+            .status();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn allowed_host_expiry_test() -> anyhow::Result<()> {
+        let server = MockServer::start().await;
+        mock_auth_routes(&server);
+        let radiko =
+            Radiko::new_with_endpoint_config(EndpointConfig::single_host(server.base_url()))
+                .await?;
+        let relay = radiko.relay().await;
+
+        relay.relay_uri(SEGMENT_PATH, "https://old.example.com/0.aac")?;
+        assert!(relay.is_allowed("https://old.example.com/1.aac"));
+
+        // 最近のプレイリストに含まれていないホストは拒否し、次の追加時に取り除く
+        let expired = Instant::now()
+            .checked_sub(ALLOWED_HOST_TTL + Duration::from_secs(1))
+            .unwrap();
+        relay
+            .inner
+            .allowed_hosts
+            .write()
+            .unwrap()
+            .insert("old.example.com:443".to_string(), expired);
+        assert!(!relay.is_allowed("https://old.example.com/1.aac"));
+
+        relay.relay_uri(SEGMENT_PATH, "https://new.example.com/0.aac")?;
+        let allowed_hosts = relay.inner.allowed_hosts.read().unwrap();
+        assert_eq!(
+            allowed_hosts.keys().collect::<Vec<_>>(),
+            vec!["new.example.com:443"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+//! テスト用にradikoの代わりに応答するローカルHTTPサーバー
+//! `EndpointConfig::single_host(server.base_url())` と組み合わせてオフラインでテストする
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// クエリ文字列を含むパス
+    pub target: String,
+    pub headers: HashMap<String, String>,
+}
+
+impl RecordedRequest {
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    /// パスごとの応答。複数登録されている場合は先頭から順に返し、最後の1件は返し続ける
+    routes: HashMap<String, VecDeque<MockResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+#[derive(Clone)]
+pub struct MockServer {
+    base_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let accept_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let state = accept_state.clone();
+                tokio::spawn(async move {
+                    let _ = Self::handle(socket, state).await;
+                });
+            }
+        });
+
+        Self { base_url, state }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn route(&self, path: &str, response: MockResponse) -> &Self {
+        self.state
+            .lock()
+            .unwrap()
+            .routes
+            .entry(path.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path() == path)
+            .collect()
+    }
+
+    async fn handle(mut socket: TcpStream, state: Arc<Mutex<MockState>>) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = socket.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default().to_string();
+        let target = request_line.next().unwrap_or_default().to_string();
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let content_length = headers
+            .get("content-length")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(0);
+        let mut body_len = buf.len() - header_end;
+        while body_len < content_length {
+            let n = socket.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            body_len += n;
+        }
+
+        let request = RecordedRequest {
+            method,
+            target,
+            headers,
+        };
+        let response = {
+            let mut state = state.lock().unwrap();
+            let response = match state.routes.get_mut(request.path()) {
+                Some(responses) if responses.len() > 1 => responses.pop_front(),
+                Some(responses) => responses.front().cloned(),
+                None => None,
+            };
+            state.requests.push(request);
+            response.unwrap_or_else(|| MockResponse::status(404))
+        };
+
+        let mut raw = format!(
+            "HTTP/1.1 {} MOCK\r\nContent-Length: {}\r\nConnection: close\r\n",
+            response.status,
+            response.body.len()
+        );
+        for (name, value) in &response.headers {
+            raw.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        raw.push_str("\r\n");
+        socket.write_all(raw.as_bytes()).await?;
+        socket.write_all(&response.body).await?;
+        socket.shutdown().await
+    }
+}
+
+/// auth1/auth2までの認証フローに応答するルートを登録する
+pub fn mock_auth_routes(server: &MockServer) {
+    server
+        .route(
+            "/area/",
+            MockResponse::ok(r#"document.write('<span class="JP13">TOKYO JAPAN</span>');"#),
+        )
+        .route(
+            "/apps/js/playerCommon.js",
+            MockResponse::ok(
+                "var player = new RadikoJSPlayer($audio[0], 'pc_html5', 'bcd151073c03b352e1ef2fd66c32209da9ca0afa', {",
+            ),
+        )
+        .route(
+            "/v2/api/auth1",
+            MockResponse::ok("")
+                .header("X-Radiko-Authtoken", "mock_token")
+                .header("X-Radiko-KeyOffset", "8")
+                .header("X-Radiko-KeyLength", "16"),
+        )
+        .route("/v2/api/auth2", MockResponse::ok("JP13,東京都,tokyo Japan"));
+}
+
+/// エリアフリーのログインとログインチェックに応答するルートを登録する
+pub fn mock_login_routes(server: &MockServer) {
+    server
+        .route(
+            "/v4/api/member/login",
+            MockResponse::ok(
+                r#"{"twitter_name":null,"status":"200","unpaid":"0","radiko_session":"mock_session","areafree":"1","member_ukey":"mock_ukey","facebook_name":null,"privileges":["areafree"],"paid_member":"1"}"#,
+            ),
+        )
+        .route(
+            "/ap/member/webapi/v2/member/login/check",
+            MockResponse::ok(r#"{"status":"200"}"#),
+        );
+}
+
+/// 5秒のセグメントを`segments/{sequence}.aac`として並べたメディアプレイリスト
+pub fn mock_media_playlist(first_sequence: u64, count: u64, ended: bool) -> String {
+    let mut content = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:5\n#EXT-X-MEDIA-SEQUENCE:{}\n",
+        first_sequence
+    );
+    for sequence in first_sequence..first_sequence + count {
+        content.push_str(&format!("#EXTINF:5,\nsegments/{}.aac\n", sequence));
+    }
+    if ended {
+        content.push_str("#EXT-X-ENDLIST\n");
+    }
+    content
+}
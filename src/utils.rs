@@ -1,3 +1,4 @@
+use crate::error::{RadikoError, Result};
 use chrono::Utc;
 use md5::{Digest, Md5};
 use rand::Rng;
@@ -31,3 +32,20 @@ pub fn load_env() {
         Err(e) => println!("load dotenv error path: {}, error: {}", dotenv_path, e),
     }
 }
+
+/// ステータスコードが成功以外の場合はレスポンスボディを含めてエラーにする
+pub async fn ensure_success(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let url = response.url().to_string();
+    let body = response.text().await.unwrap_or_default();
+    Err(RadikoError::HttpStatus { status, url, body })
+}
+
+pub fn md5_hex(input: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(input.as_bytes());
+    format!("{:x}", hasher.finalize())
+}